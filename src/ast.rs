@@ -1,35 +1,99 @@
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
 use super::token::{Token, Literal};
 use super::errors::LoxError;
+use super::span::Span;
+
+/// Identifies an expression node that refers to a variable, so the resolver can
+/// record its binding distance. Ids are unique for the lifetime of the process,
+/// which keeps them stable across REPL lines parsed by different parsers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct ExprId(pub usize);
+
+impl ExprId {
+    pub fn next() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        ExprId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// A deserialized node gets a fresh id rather than the serialized one, which could
+/// collide with ids already handed out in this process.
+impl<'de> Deserialize<'de> for ExprId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        usize::deserialize(deserializer)?;
+        Ok(ExprId::next())
+    }
+}
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind")]
 pub enum Expr {
     Binary(BinaryExpr),
     Grouping(GroupingExpr),
     Literal(LiteralExpr),
     Unary(UnaryExpr),
+    Variable(VariableExpr),
+    Assign(AssignExpr),
+    Logical(LogicalExpr),
+    Call(CallExpr),
+    Get(GetExpr),
+    Set(SetExpr),
+    This(ThisExpr),
+    Super(SuperExpr),
 }
 
 impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary(e) => e.span,
+            Expr::Grouping(e) => e.span,
+            Expr::Literal(e) => e.span,
+            Expr::Unary(e) => e.span,
+            Expr::Variable(e) => e.span,
+            Expr::Assign(e) => e.span,
+            Expr::Logical(e) => e.span,
+            Expr::Call(e) => e.span,
+            Expr::Get(e) => e.span,
+            Expr::Set(e) => e.span,
+            Expr::This(e) => e.span,
+            Expr::Super(e) => e.span,
+        }
+    }
+
     pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> Result<T, LoxError> {
         match self {
             Expr::Binary(e) => e.accept(visitor),
             Expr::Grouping(e) => e.accept(visitor),
             Expr::Literal(e) => e.accept(visitor),
             Expr::Unary(e) => e.accept(visitor),
+            Expr::Variable(e) => e.accept(visitor),
+            Expr::Assign(e) => e.accept(visitor),
+            Expr::Logical(e) => e.accept(visitor),
+            Expr::Call(e) => e.accept(visitor),
+            Expr::Get(e) => e.accept(visitor),
+            Expr::Set(e) => e.accept(visitor),
+            Expr::This(e) => e.accept(visitor),
+            Expr::Super(e) => e.accept(visitor),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct BinaryExpr {
     pub left: Box<Expr>,
     pub operator: Token,
     pub right: Box<Expr>,
+    pub span: Span,
 }
 
 impl BinaryExpr {
     pub fn new(left: Expr, operator: Token, right: Expr) -> Self {
         Self {
+            span: left.span().merge(right.span()),
             left: Box::new(left),
             operator,
             right: Box::new(right),
@@ -37,52 +101,225 @@ impl BinaryExpr {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GroupingExpr {
-    pub expression: Box<Expr>
+    pub expression: Box<Expr>,
+    pub span: Span,
 }
 
 impl GroupingExpr {
-    pub fn new(expression: Expr) -> Self {
+    /// `span` covers the parentheses as well as the inner expression.
+    pub fn new(expression: Expr, span: Span) -> Self {
         Self {
             expression: Box::new(expression),
+            span,
         } 
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct LiteralExpr {
     pub value: Option<Literal>,
+    pub span: Span,
 }
 
 impl LiteralExpr {
-    pub fn new(value: Option<Literal>) -> Self {
+    pub fn new(value: Option<Literal>, span: Span) -> Self {
         Self {
             value,
+            span,
+        }
+    }
+
+    /// The value as it would be written in Lox source.
+    pub fn source_text(&self) -> String {
+        match &self.value {
+            None => "nil".to_string(),
+            Some(Literal::True) => "true".to_string(),
+            Some(Literal::False) => "false".to_string(),
+            Some(Literal::Number(n)) => n.to_string(),
+            Some(Literal::Str(s)) => format!("\"{s}\""),
+            Some(Literal::Identifier(name)) => name.to_string(),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct UnaryExpr {
     pub operator: Token,
     pub right: Box<Expr>,
+    pub span: Span,
 }
 
 impl UnaryExpr {
     pub fn new(operator: Token, right: Expr) -> Self {
         Self {
+            span: operator.span.merge(right.span()),
             operator,
             right: Box::new(right),
         }
     } 
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VariableExpr {
+    pub id: ExprId,
+    pub name: Token,
+    pub span: Span,
+}
+
+impl VariableExpr {
+    pub fn new(name: Token) -> Self {
+        Self {
+            id: ExprId::next(),
+            span: name.span,
+            name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssignExpr {
+    pub id: ExprId,
+    pub name: Token,
+    pub value: Box<Expr>,
+    pub span: Span,
+}
+
+impl AssignExpr {
+    pub fn new(name: Token, value: Expr) -> Self {
+        Self {
+            id: ExprId::next(),
+            span: name.span.merge(value.span()),
+            name,
+            value: Box::new(value),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogicalExpr {
+    pub left: Box<Expr>,
+    pub operator: Token,
+    pub right: Box<Expr>,
+    pub span: Span,
+}
+
+impl LogicalExpr {
+    pub fn new(left: Expr, operator: Token, right: Expr) -> Self {
+        Self {
+            span: left.span().merge(right.span()),
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CallExpr {
+    pub callee: Box<Expr>,
+    pub paren: Token,
+    pub arguments: Vec<Expr>,
+    pub span: Span,
+}
+
+impl CallExpr {
+    pub fn new(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Self {
+        Self {
+            span: callee.span().merge(paren.span),
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub span: Span,
+}
+
+impl GetExpr {
+    pub fn new(object: Expr, name: Token) -> Self {
+        Self {
+            span: object.span().merge(name.span),
+            object: Box::new(object),
+            name,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetExpr {
+    pub object: Box<Expr>,
+    pub name: Token,
+    pub value: Box<Expr>,
+    pub span: Span,
+}
+
+impl SetExpr {
+    pub fn new(object: Expr, name: Token, value: Expr) -> Self {
+        Self {
+            span: object.span().merge(value.span()),
+            object: Box::new(object),
+            name,
+            value: Box::new(value),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThisExpr {
+    pub id: ExprId,
+    pub keyword: Token,
+    pub span: Span,
+}
+
+impl ThisExpr {
+    pub fn new(keyword: Token) -> Self {
+        Self {
+            id: ExprId::next(),
+            span: keyword.span,
+            keyword,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SuperExpr {
+    pub id: ExprId,
+    pub keyword: Token,
+    pub method: Token,
+    pub span: Span,
+}
+
+impl SuperExpr {
+    pub fn new(keyword: Token, method: Token) -> Self {
+        Self {
+            id: ExprId::next(),
+            span: keyword.span.merge(method.span),
+            keyword,
+            method,
+        }
+    }
+}
+
 pub trait Visitor<T> {
     fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<T, LoxError>;
     fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<T, LoxError>;
     fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<T, LoxError>;
     fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<T, LoxError>;
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<T, LoxError>;
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<T, LoxError>;
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<T, LoxError>;
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<T, LoxError>;
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<T, LoxError>;
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<T, LoxError>;
+    fn visit_this_expr(&self, expr: &ThisExpr) -> Result<T, LoxError>;
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<T, LoxError>;
 }
 
 impl BinaryExpr {
@@ -109,3 +346,333 @@ impl UnaryExpr {
     }
 }
 
+impl VariableExpr {
+    pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> Result<T, LoxError> {
+        visitor.visit_variable_expr(self)
+    }
+}
+
+impl AssignExpr {
+    pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> Result<T, LoxError> {
+        visitor.visit_assign_expr(self)
+    }
+}
+
+impl LogicalExpr {
+    pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> Result<T, LoxError> {
+        visitor.visit_logical_expr(self)
+    }
+}
+
+impl CallExpr {
+    pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> Result<T, LoxError> {
+        visitor.visit_call_expr(self)
+    }
+}
+
+impl GetExpr {
+    pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> Result<T, LoxError> {
+        visitor.visit_get_expr(self)
+    }
+}
+
+impl SetExpr {
+    pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> Result<T, LoxError> {
+        visitor.visit_set_expr(self)
+    }
+}
+
+impl ThisExpr {
+    pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> Result<T, LoxError> {
+        visitor.visit_this_expr(self)
+    }
+}
+
+impl SuperExpr {
+    pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> Result<T, LoxError> {
+        visitor.visit_super_expr(self)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Stmt {
+    Expression(ExpressionStmt),
+    Print(PrintStmt),
+    Var(VarStmt),
+    Block(BlockStmt),
+    If(IfStmt),
+    While(WhileStmt),
+    Function(FunctionStmt),
+    Return(ReturnStmt),
+    Class(ClassStmt),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Expression(s) => s.span,
+            Stmt::Print(s) => s.span,
+            Stmt::Var(s) => s.span,
+            Stmt::Block(s) => s.span,
+            Stmt::If(s) => s.span,
+            Stmt::While(s) => s.span,
+            Stmt::Function(s) => s.span,
+            Stmt::Return(s) => s.span,
+            Stmt::Class(s) => s.span,
+        }
+    }
+
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+        match self {
+            Stmt::Expression(s) => s.accept(visitor),
+            Stmt::Print(s) => s.accept(visitor),
+            Stmt::Var(s) => s.accept(visitor),
+            Stmt::Block(s) => s.accept(visitor),
+            Stmt::If(s) => s.accept(visitor),
+            Stmt::While(s) => s.accept(visitor),
+            Stmt::Function(s) => s.accept(visitor),
+            Stmt::Return(s) => s.accept(visitor),
+            Stmt::Class(s) => s.accept(visitor),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExpressionStmt {
+    pub expression: Expr,
+    pub span: Span,
+}
+
+impl ExpressionStmt {
+    pub fn new(expression: Expr, span: Span) -> Self {
+        Self {
+            expression,
+            span,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrintStmt {
+    pub expression: Expr,
+    pub span: Span,
+}
+
+impl PrintStmt {
+    pub fn new(expression: Expr, span: Span) -> Self {
+        Self {
+            expression,
+            span,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VarStmt {
+    pub name: Token,
+    pub initializer: Option<Expr>,
+    pub span: Span,
+}
+
+impl VarStmt {
+    pub fn new(name: Token, initializer: Option<Expr>, span: Span) -> Self {
+        Self {
+            name,
+            initializer,
+            span,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlockStmt {
+    pub statements: Vec<Stmt>,
+    pub span: Span,
+}
+
+impl BlockStmt {
+    pub fn new(statements: Vec<Stmt>, span: Span) -> Self {
+        Self {
+            statements,
+            span,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IfStmt {
+    pub condition: Expr,
+    pub then_branch: Box<Stmt>,
+    pub else_branch: Option<Box<Stmt>>,
+    pub span: Span,
+}
+
+impl IfStmt {
+    pub fn new(condition: Expr, then_branch: Stmt, else_branch: Option<Stmt>, span: Span) -> Self {
+        Self {
+            condition,
+            then_branch: Box::new(then_branch),
+            else_branch: else_branch.map(Box::new),
+            span,
+        }
+    }
+}
+
+/// `desugared_for` is set when the parser built this loop from a `for`, so that
+/// tools such as the formatter can print it back.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WhileStmt {
+    pub condition: Expr,
+    pub body: Box<Stmt>,
+    pub desugared_for: Option<ForClauses>,
+    pub span: Span,
+}
+
+impl WhileStmt {
+    pub fn new(condition: Expr, body: Stmt, span: Span) -> Self {
+        Self {
+            condition,
+            body: Box::new(body),
+            desugared_for: None,
+            span,
+        }
+    }
+
+    pub fn desugared_from(mut self, clauses: ForClauses) -> Self {
+        self.desugared_for = Some(clauses);
+        self
+    }
+}
+
+/// Which clauses a desugared `for` loop had. With an initializer the loop is the
+/// second statement of a block holding it; with an increment the body is a block
+/// ending in it; without a condition the condition is a synthesized `true`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForClauses {
+    pub initializer: bool,
+    pub condition: bool,
+    pub increment: bool,
+}
+
+/// The body is reference counted so that runtime functions can share it with the AST.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionStmt {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Rc<Vec<Stmt>>,
+    pub span: Span,
+}
+
+impl FunctionStmt {
+    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>, span: Span) -> Self {
+        Self {
+            name,
+            params,
+            body: Rc::new(body),
+            span,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReturnStmt {
+    pub keyword: Token,
+    pub value: Option<Expr>,
+    pub span: Span,
+}
+
+impl ReturnStmt {
+    pub fn new(keyword: Token, value: Option<Expr>, span: Span) -> Self {
+        Self {
+            keyword,
+            value,
+            span,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClassStmt {
+    pub name: Token,
+    pub superclass: Option<VariableExpr>,
+    pub methods: Vec<FunctionStmt>,
+    pub span: Span,
+}
+
+impl ClassStmt {
+    pub fn new(name: Token, superclass: Option<VariableExpr>, methods: Vec<FunctionStmt>, span: Span) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+            span,
+        }
+    }
+}
+
+pub trait StmtVisitor<T> {
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<T, LoxError>;
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<T, LoxError>;
+    fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<T, LoxError>;
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<T, LoxError>;
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<T, LoxError>;
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<T, LoxError>;
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<T, LoxError>;
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<T, LoxError>;
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<T, LoxError>;
+}
+
+impl ExpressionStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+        visitor.visit_expression_stmt(self)
+    }
+}
+
+impl PrintStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+        visitor.visit_print_stmt(self)
+    }
+}
+
+impl VarStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+        visitor.visit_var_stmt(self)
+    }
+}
+
+impl BlockStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+        visitor.visit_block_stmt(self)
+    }
+}
+
+impl IfStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+        visitor.visit_if_stmt(self)
+    }
+}
+
+impl WhileStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+        visitor.visit_while_stmt(self)
+    }
+}
+
+impl FunctionStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+        visitor.visit_function_stmt(self)
+    }
+}
+
+impl ReturnStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+        visitor.visit_return_stmt(self)
+    }
+}
+
+impl ClassStmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> Result<T, LoxError> {
+        visitor.visit_class_stmt(self)
+    }
+}
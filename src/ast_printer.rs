@@ -38,11 +38,46 @@ impl Visitor<String> for AstPrinter {
     fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<String, LoxError> {
         self.parenthesize(expr.operator.lexeme.to_string(), vec![&expr.right])
     }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<String, LoxError> {
+        Ok(expr.name.lexeme.to_string())
+    }
+
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<String, LoxError> {
+        self.parenthesize(format!("= {}", expr.name.lexeme), vec![&expr.value])
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<String, LoxError> {
+        self.parenthesize(expr.operator.lexeme.to_string(), vec![&expr.left, &expr.right])
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<String, LoxError> {
+        let mut exprs = vec![expr.callee.as_ref()];
+        exprs.extend(expr.arguments.iter());
+        self.parenthesize("call".to_string(), exprs)
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<String, LoxError> {
+        self.parenthesize(format!(". {}", expr.name.lexeme), vec![&expr.object])
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<String, LoxError> {
+        self.parenthesize(format!("set {}", expr.name.lexeme), vec![&expr.object, &expr.value])
+    }
+
+    fn visit_this_expr(&self, _expr: &ThisExpr) -> Result<String, LoxError> {
+        Ok("this".to_string())
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<String, LoxError> {
+        Ok(format!("(super {})", expr.method.lexeme))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::span::Span;
     use crate::token::*;
     use crate::token_type::*;
 
@@ -50,18 +85,21 @@ mod tests {
     fn test_string() {
         let left = UnaryExpr {
             operator: Token::new(TokenType::Minus, '-'.to_string(), None, 1),
-            right: Box::new(Expr::Literal(LiteralExpr { value: Some(Literal::Number(123.0)) }))
+            right: Box::new(Expr::Literal(LiteralExpr { value: Some(Literal::Number(123.0)), span: Span::default() })),
+            span: Span::default(),
         };
         let operator = Token::new(TokenType::Star, '*'.to_string(), None, 1);
         let right = GroupingExpr {
-            expression: Box::new(Expr::Literal(LiteralExpr { value: Some(Literal::Number(45.67)) }))
+            expression: Box::new(Expr::Literal(LiteralExpr { value: Some(Literal::Number(45.67)), span: Span::default() })),
+            span: Span::default(),
         };
 
         let expr = Expr::Binary(
             BinaryExpr { 
                 left: Box::new(Expr::Unary(left)), 
                 operator, 
-                right: Box::new(Expr::Grouping(right)) 
+                right: Box::new(Expr::Grouping(right)),
+                span: Span::default(),
             }
         );
 
@@ -0,0 +1,99 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use super::ast::FunctionStmt;
+use super::class::LoxInstance;
+use super::environment::Environment;
+use super::errors::LoxError;
+use super::interpreter::Interpreter;
+use super::symbol::Symbol;
+use super::value::Value;
+
+pub trait LoxCallable: fmt::Debug + fmt::Display {
+    fn arity(&self) -> usize;
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, LoxError>;
+}
+
+#[derive(Debug)]
+pub struct LoxFunction {
+    declaration: FunctionStmt,
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(declaration: FunctionStmt, closure: Rc<RefCell<Environment>>, is_initializer: bool) -> Self {
+        Self {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Returns a copy of this method whose closure has `this` bound to `instance`.
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        environment.define(Symbol::THIS, Value::Instance(instance));
+        LoxFunction::new(self.declaration.clone(), Rc::new(RefCell::new(environment)), self.is_initializer)
+    }
+
+    fn bound_this(&self) -> Value {
+        self.closure.borrow().get_at(0, Symbol::THIS).unwrap_or(Value::Nil)
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, LoxError> {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(param.lexeme.symbol(), argument);
+        }
+
+        match interpreter.execute_block(&self.declaration.body, environment) {
+            Ok(()) | Err(LoxError::Return(_)) if self.is_initializer => Ok(self.bound_this()),
+            Ok(()) => Ok(Value::Nil),
+            Err(LoxError::Return(value)) => Ok(value),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
+
+/// A function implemented in Rust and exposed to Lox code as a global.
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: fn(&[Value]) -> Value,
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, LoxError> {
+        Ok((self.function)(&arguments))
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction").field("name", &self.name).field("arity", &self.arity).finish()
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use super::symbol::Symbol;
+use super::token::Token;
+
+/// Instructions of the bytecode VM. Each is one byte, followed by the operands
+/// listed in its doc comment; `u16` operands are big-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OpCode {
+    /// `u16` constant index. Pushes the constant.
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    /// `u8` stack slot, relative to the current frame.
+    GetLocal,
+    /// `u8` stack slot. Leaves the assigned value on the stack.
+    SetLocal,
+    /// `u16` constant index of the name.
+    GetGlobal,
+    /// `u16` constant index of the name. Pops the value.
+    DefineGlobal,
+    /// `u16` constant index of the name. Leaves the assigned value on the stack.
+    SetGlobal,
+    /// `u8` index into the current closure's upvalues.
+    GetUpvalue,
+    /// `u8` upvalue index. Leaves the assigned value on the stack.
+    SetUpvalue,
+    /// `u16` constant index of the property name.
+    GetProperty,
+    /// `u16` constant index of the property name. Pops the instance and the value,
+    /// then pushes the value back.
+    SetProperty,
+    /// `u16` constant index of the method name. Pops the superclass and the
+    /// receiver, pushes the bound method.
+    GetSuper,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    Print,
+    /// `u16` forward offset.
+    Jump,
+    /// `u16` forward offset. Jumps if the top of the stack is falsey, without
+    /// popping it.
+    JumpIfFalse,
+    /// `u16` backward offset.
+    Loop,
+    /// `u8` argument count.
+    Call,
+    /// `u16` constant index of a function. Pushes a new closure over it, capturing
+    /// the upvalues its descriptors name.
+    Closure,
+    /// Moves the local on top of the stack into the heap for the closures that
+    /// captured it, then pops it.
+    CloseUpvalue,
+    Return,
+    /// `u16` constant index of the class name.
+    Class,
+    /// Pops a subclass and copies the methods of the superclass below it into it.
+    Inherit,
+    /// `u16` constant index of the method name. Pops a closure into the class below it.
+    Method,
+}
+
+impl OpCode {
+    /// Every opcode in declaration order, so that a byte indexes its opcode.
+    const ALL: [OpCode; 38] = [
+        OpCode::Constant,
+        OpCode::Nil,
+        OpCode::True,
+        OpCode::False,
+        OpCode::Pop,
+        OpCode::GetLocal,
+        OpCode::SetLocal,
+        OpCode::GetGlobal,
+        OpCode::DefineGlobal,
+        OpCode::SetGlobal,
+        OpCode::GetUpvalue,
+        OpCode::SetUpvalue,
+        OpCode::GetProperty,
+        OpCode::SetProperty,
+        OpCode::GetSuper,
+        OpCode::Equal,
+        OpCode::NotEqual,
+        OpCode::Greater,
+        OpCode::GreaterEqual,
+        OpCode::Less,
+        OpCode::LessEqual,
+        OpCode::Add,
+        OpCode::Subtract,
+        OpCode::Multiply,
+        OpCode::Divide,
+        OpCode::Not,
+        OpCode::Negate,
+        OpCode::Print,
+        OpCode::Jump,
+        OpCode::JumpIfFalse,
+        OpCode::Loop,
+        OpCode::Call,
+        OpCode::Closure,
+        OpCode::CloseUpvalue,
+        OpCode::Return,
+        OpCode::Class,
+        OpCode::Inherit,
+        OpCode::Method,
+    ];
+
+    pub fn from_byte(byte: u8) -> Option<OpCode> {
+        Self::ALL.get(byte as usize).copied().filter(|op| *op as u8 == byte)
+    }
+}
+
+/// A constant-pool entry. Functions are compiled code, shared by every closure
+/// created over them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Constant {
+    Number(f64),
+    Str(Symbol),
+    Function(Rc<Function>),
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constant::Number(n) => write!(f, "{n}"),
+            Constant::Str(s) => write!(f, "{s}"),
+            Constant::Function(function) => write!(f, "{function}"),
+        }
+    }
+}
+
+/// Where a closure finds each variable it captures: a local slot of the function
+/// that creates it, or one of that function's own upvalues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpvalueDescriptor {
+    pub is_local: bool,
+    pub index: u8,
+}
+
+/// A compiled function body. The top-level script is a function with an empty name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    pub upvalues: Vec<UpvalueDescriptor>,
+    pub chunk: Chunk,
+}
+
+impl Function {
+    pub fn new(name: &str, arity: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            upvalues: Vec::new(),
+            chunk: Chunk::default(),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "<script>")
+        } else {
+            write!(f, "<fn {}>", self.name)
+        }
+    }
+}
+
+/// A sequence of bytecode with its constant pool and source information.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Constant>,
+    /// Run-length line table: the offset of the first instruction of each run of
+    /// instructions on the same line, with that line.
+    lines: Vec<(usize, u64)>,
+    /// For each instruction that can fail at runtime, sorted by offset, the token
+    /// its error is reported at.
+    sites: Vec<(usize, Token)>,
+}
+
+impl Chunk {
+    /// Appends an instruction compiled from source `line`.
+    pub fn write_op(&mut self, op: OpCode, line: u64) {
+        if self.lines.last().is_none_or(|&(_, last)| last != line) {
+            self.lines.push((self.code.len(), line));
+        }
+        self.code.push(op as u8);
+    }
+
+    /// Appends an instruction that reports runtime errors at `token`.
+    pub fn write_op_at(&mut self, op: OpCode, token: &Token) {
+        self.sites.push((self.code.len(), token.clone()));
+        self.write_op(op, token.line);
+    }
+
+    pub fn write_byte(&mut self, byte: u8) {
+        self.code.push(byte);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.code.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+
+    /// Overwrites the `u16` operand at `offset`, for jumps whose target was not
+    /// known when they were emitted.
+    pub fn patch_u16(&mut self, offset: usize, value: u16) {
+        self.code[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+    }
+
+    /// Index of `constant` in the pool, adding it unless an equal number or string
+    /// is already there. `None` once the pool is full.
+    pub fn add_constant(&mut self, constant: Constant) -> Option<u16> {
+        let existing = self.constants.iter().position(|c| match (c, &constant) {
+            (Constant::Number(a), Constant::Number(b)) => a.to_bits() == b.to_bits(),
+            (Constant::Str(a), Constant::Str(b)) => a == b,
+            _ => false,
+        });
+        let index = existing.unwrap_or_else(|| {
+            self.constants.push(constant);
+            self.constants.len() - 1
+        });
+        u16::try_from(index).ok()
+    }
+
+    /// Source line of the instruction at `offset`.
+    pub fn line(&self, offset: usize) -> u64 {
+        let run = self.lines.partition_point(|&(start, _)| start <= offset);
+        run.checked_sub(1).map_or(0, |run| self.lines[run].1)
+    }
+
+    /// Token a runtime error raised by the instruction at `offset` is reported at.
+    pub fn site(&self, offset: usize) -> Option<&Token> {
+        self.sites
+            .binary_search_by_key(&offset, |(start, _)| *start)
+            .ok()
+            .map(|index| &self.sites[index].1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token_type::TokenType;
+
+    #[test]
+    fn test_opcodes_round_trip_through_bytes() {
+        for byte in 0..=OpCode::Method as u8 {
+            assert_eq!(OpCode::from_byte(byte).map(|op| op as u8), Some(byte));
+        }
+        assert_eq!(OpCode::from_byte(OpCode::Method as u8 + 1), None);
+    }
+
+    #[test]
+    fn test_line_table_and_sites() {
+        let mut chunk = Chunk::default();
+        let index = chunk.add_constant(Constant::Number(1.0)).unwrap();
+        chunk.write_op(OpCode::Constant, 1);
+        chunk.write_u16(index);
+        chunk.write_op_at(OpCode::Negate, &Token::new(TokenType::Minus, "-".to_string(), None, 1));
+        chunk.write_op(OpCode::Print, 2);
+        chunk.write_op(OpCode::Nil, 4);
+
+        assert_eq!(chunk.add_constant(Constant::Number(1.0)), Some(index));
+        assert_eq!(chunk.read_u16(1), index);
+        assert_eq!((0..chunk.code.len()).map(|offset| chunk.line(offset)).collect::<Vec<_>>(), [1, 1, 1, 1, 2, 4]);
+        assert_eq!(chunk.site(3).map(|token| token.lexeme.as_str()), Some("-"));
+        assert!(chunk.site(0).is_none());
+    }
+}
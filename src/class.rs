@@ -0,0 +1,92 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use super::callable::{LoxCallable, LoxFunction};
+use super::errors::LoxError;
+use super::interpreter::Interpreter;
+use super::symbol::Symbol;
+use super::token::Token;
+use super::value::Value;
+
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: Symbol,
+    pub superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<Symbol, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(name: Symbol, superclass: Option<Rc<LoxClass>>, methods: HashMap<Symbol, Rc<LoxFunction>>) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    pub fn find_method(&self, name: Symbol) -> Option<Rc<LoxFunction>> {
+        match self.methods.get(&name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref().and_then(|superclass| superclass.find_method(name)),
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.find_method(Symbol::INIT).map_or(0, |initializer| initializer.arity())
+    }
+
+    /// Creates a new instance and runs `init` on it, if the class defines one.
+    pub fn instantiate(class: &Rc<LoxClass>, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, LoxError> {
+        let instance = Rc::new(RefCell::new(LoxInstance::new(class.clone())));
+        if let Some(initializer) = class.find_method(Symbol::INIT) {
+            initializer.bind(instance.clone()).call(interpreter, arguments)?;
+        }
+        Ok(Value::Instance(instance))
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct LoxInstance {
+    pub class: Rc<LoxClass>,
+    fields: HashMap<Symbol, Value>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Looks up a field, falling back to a method bound to `instance`.
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, name: &Token) -> Result<Value, LoxError> {
+        if let Some(value) = instance.borrow().fields.get(&name.lexeme.symbol()) {
+            return Ok(value.clone());
+        }
+
+        let method = instance.borrow().class.find_method(name.lexeme.symbol());
+        match method {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(instance.clone())))),
+            None => Err(LoxError::UndefinedProperty(name.clone())),
+        }
+    }
+
+    pub fn set(&mut self, name: &Token, value: Value) {
+        self.fields.insert(name.lexeme.symbol(), value);
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
@@ -0,0 +1,627 @@
+use std::fmt;
+
+/// Stable identifiers for every kind of diagnostic. The numbering is grouped by
+/// phase: `L00xx` scanning, `L01xx` parsing, `L02xx` resolving and `L03xx` runtime.
+/// Codes are never reused or renumbered once released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    UnterminatedString,
+    UnexpectedCharacter,
+    UnterminatedBlockComment,
+    InvalidUtf8,
+    ExpectedExpression,
+    ExpectedRightParen,
+    ExpectedRightBrace,
+    ExpectedSemicolon,
+    ExpectedLeftParen,
+    ExpectedLeftBrace,
+    ExpectedIdentifier,
+    InvalidAssignmentTarget,
+    TooManyArguments,
+    ExpectedEndOfExpression,
+    ExpectedDot,
+    ReadInOwnInitializer,
+    AlreadyDeclared,
+    TopLevelReturn,
+    ReturnFromInitializer,
+    ThisOutsideClass,
+    SuperOutsideClass,
+    SuperWithoutSuperclass,
+    InheritFromSelf,
+    OperandMustBeNumber,
+    OperandsMustBeNumbersOrStrings,
+    UndefinedVariable,
+    NotCallable,
+    ArityMismatch,
+    OnlyInstancesHaveProperties,
+    UndefinedProperty,
+    SuperclassMustBeClass,
+    StackOverflow,
+    OperandsMustBeNumbers,
+    OnlyInstancesHaveFields,
+    CompilerLimit,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 35] = [
+        ErrorCode::UnterminatedString,
+        ErrorCode::UnexpectedCharacter,
+        ErrorCode::UnterminatedBlockComment,
+        ErrorCode::InvalidUtf8,
+        ErrorCode::ExpectedExpression,
+        ErrorCode::ExpectedRightParen,
+        ErrorCode::ExpectedRightBrace,
+        ErrorCode::ExpectedSemicolon,
+        ErrorCode::ExpectedLeftParen,
+        ErrorCode::ExpectedLeftBrace,
+        ErrorCode::ExpectedIdentifier,
+        ErrorCode::InvalidAssignmentTarget,
+        ErrorCode::TooManyArguments,
+        ErrorCode::ExpectedEndOfExpression,
+        ErrorCode::ExpectedDot,
+        ErrorCode::ReadInOwnInitializer,
+        ErrorCode::AlreadyDeclared,
+        ErrorCode::TopLevelReturn,
+        ErrorCode::ReturnFromInitializer,
+        ErrorCode::ThisOutsideClass,
+        ErrorCode::SuperOutsideClass,
+        ErrorCode::SuperWithoutSuperclass,
+        ErrorCode::InheritFromSelf,
+        ErrorCode::OperandMustBeNumber,
+        ErrorCode::OperandsMustBeNumbersOrStrings,
+        ErrorCode::UndefinedVariable,
+        ErrorCode::NotCallable,
+        ErrorCode::ArityMismatch,
+        ErrorCode::OnlyInstancesHaveProperties,
+        ErrorCode::UndefinedProperty,
+        ErrorCode::SuperclassMustBeClass,
+        ErrorCode::StackOverflow,
+        ErrorCode::OperandsMustBeNumbers,
+        ErrorCode::OnlyInstancesHaveFields,
+        ErrorCode::CompilerLimit,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnterminatedString => "L0001",
+            ErrorCode::UnexpectedCharacter => "L0002",
+            ErrorCode::UnterminatedBlockComment => "L0003",
+            ErrorCode::InvalidUtf8 => "L0004",
+            ErrorCode::ExpectedExpression => "L0101",
+            ErrorCode::ExpectedRightParen => "L0102",
+            ErrorCode::ExpectedRightBrace => "L0103",
+            ErrorCode::ExpectedSemicolon => "L0104",
+            ErrorCode::ExpectedLeftParen => "L0105",
+            ErrorCode::ExpectedLeftBrace => "L0106",
+            ErrorCode::ExpectedIdentifier => "L0107",
+            ErrorCode::InvalidAssignmentTarget => "L0108",
+            ErrorCode::TooManyArguments => "L0109",
+            ErrorCode::ExpectedEndOfExpression => "L0110",
+            ErrorCode::ExpectedDot => "L0111",
+            ErrorCode::ReadInOwnInitializer => "L0201",
+            ErrorCode::AlreadyDeclared => "L0202",
+            ErrorCode::TopLevelReturn => "L0203",
+            ErrorCode::ReturnFromInitializer => "L0204",
+            ErrorCode::ThisOutsideClass => "L0205",
+            ErrorCode::SuperOutsideClass => "L0206",
+            ErrorCode::SuperWithoutSuperclass => "L0207",
+            ErrorCode::InheritFromSelf => "L0208",
+            ErrorCode::OperandMustBeNumber => "L0301",
+            ErrorCode::OperandsMustBeNumbersOrStrings => "L0302",
+            ErrorCode::UndefinedVariable => "L0303",
+            ErrorCode::NotCallable => "L0304",
+            ErrorCode::ArityMismatch => "L0305",
+            ErrorCode::OnlyInstancesHaveProperties => "L0306",
+            ErrorCode::UndefinedProperty => "L0307",
+            ErrorCode::SuperclassMustBeClass => "L0308",
+            ErrorCode::StackOverflow => "L0309",
+            ErrorCode::OperandsMustBeNumbers => "L0310",
+            ErrorCode::OnlyInstancesHaveFields => "L0311",
+            ErrorCode::CompilerLimit => "L0401",
+        }
+    }
+
+    /// Looks a code up by its `Lnnnn` form, ignoring case.
+    pub fn parse(code: &str) -> Option<ErrorCode> {
+        Self::ALL.into_iter().find(|c| c.as_str().eq_ignore_ascii_case(code))
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            ErrorCode::UnterminatedString => "unterminated string",
+            ErrorCode::UnexpectedCharacter => "unexpected character",
+            ErrorCode::UnterminatedBlockComment => "unterminated block comment",
+            ErrorCode::InvalidUtf8 => "string is not valid UTF-8",
+            ErrorCode::ExpectedExpression => "expected expression",
+            ErrorCode::ExpectedRightParen => "expected ')'",
+            ErrorCode::ExpectedRightBrace => "expected '}'",
+            ErrorCode::ExpectedSemicolon => "expected ';'",
+            ErrorCode::ExpectedLeftParen => "expected '('",
+            ErrorCode::ExpectedLeftBrace => "expected '{'",
+            ErrorCode::ExpectedIdentifier => "expected a name",
+            ErrorCode::InvalidAssignmentTarget => "invalid assignment target",
+            ErrorCode::TooManyArguments => "too many parameters or arguments",
+            ErrorCode::ExpectedEndOfExpression => "expected end of expression",
+            ErrorCode::ExpectedDot => "expected '.'",
+            ErrorCode::ReadInOwnInitializer => "local variable read in its own initializer",
+            ErrorCode::AlreadyDeclared => "variable already declared in this scope",
+            ErrorCode::TopLevelReturn => "return outside of a function",
+            ErrorCode::ReturnFromInitializer => "value returned from an initializer",
+            ErrorCode::ThisOutsideClass => "'this' outside of a class",
+            ErrorCode::SuperOutsideClass => "'super' outside of a class",
+            ErrorCode::SuperWithoutSuperclass => "'super' in a class with no superclass",
+            ErrorCode::InheritFromSelf => "class inherits from itself",
+            ErrorCode::OperandMustBeNumber => "operand must be a number",
+            ErrorCode::OperandsMustBeNumbersOrStrings => "operands must be two numbers or two strings",
+            ErrorCode::UndefinedVariable => "undefined variable",
+            ErrorCode::NotCallable => "value is not callable",
+            ErrorCode::ArityMismatch => "wrong number of arguments",
+            ErrorCode::OnlyInstancesHaveProperties => "property access on a non-instance",
+            ErrorCode::UndefinedProperty => "undefined property",
+            ErrorCode::SuperclassMustBeClass => "superclass is not a class",
+            ErrorCode::StackOverflow => "stack overflow",
+            ErrorCode::OperandsMustBeNumbers => "operands must be numbers",
+            ErrorCode::OnlyInstancesHaveFields => "field assignment on a non-instance",
+            ErrorCode::CompilerLimit => "program exceeds a bytecode compiler limit",
+        }
+    }
+
+    /// Longer description printed by `--explain`, with an erroneous and a corrected
+    /// example.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            ErrorCode::UnterminatedString => "\
+A string literal was opened with '\"' but the file ended before the closing '\"'.
+Strings may span several lines, so the missing quote is often far above the end
+of the file.
+
+Erroneous code example:
+
+    print \"hello;
+
+Corrected:
+
+    print \"hello\";",
+            ErrorCode::UnexpectedCharacter => "\
+The source contains a character that is not part of any Lox token, such as '@',
+'#' or a non-ASCII letter outside of a string or comment.
+
+Erroneous code example:
+
+    var total = price # tax;
+
+Corrected:
+
+    var total = price + tax;",
+            ErrorCode::UnterminatedBlockComment => "\
+A block comment was opened with '/*' but never closed with '*/'.
+
+Erroneous code example:
+
+    /* TODO: tidy up
+    print 1;
+
+Corrected:
+
+    /* TODO: tidy up */
+    print 1;",
+            ErrorCode::InvalidUtf8 => "\
+A string literal contains bytes that are not valid UTF-8, usually because the
+file was saved in another encoding such as Latin-1. Lox strings are UTF-8.
+
+Erroneous code example, with 'é' saved as the single Latin-1 byte 0xE9:
+
+    print \"caf\\xe9\";
+
+Corrected: save the file as UTF-8, where 'é' is the two bytes 0xC3 0xA9.
+
+    print \"café\";",
+            ErrorCode::ExpectedExpression => "\
+The parser needed a value, such as a literal, a variable, a call or a
+parenthesized expression, but found something else.
+
+Erroneous code example:
+
+    var a = * 2;
+
+Corrected:
+
+    var a = 1 * 2;",
+            ErrorCode::ExpectedRightParen => "\
+A '(' was opened, for a grouping, a call, a parameter list or the clauses of
+'if', 'while' or 'for', but the matching ')' is missing. The diagnostic points at
+the opening parenthesis too.
+
+Erroneous code example:
+
+    print (1 + 2;
+
+Corrected:
+
+    print (1 + 2);",
+            ErrorCode::ExpectedRightBrace => "\
+A block or class body was opened with '{' but the matching '}' is missing.
+
+Erroneous code example:
+
+    if (ready) {
+      print \"go\";
+
+Corrected:
+
+    if (ready) {
+      print \"go\";
+    }",
+            ErrorCode::ExpectedSemicolon => "\
+Every statement that is not a block ends with ';'.
+
+Erroneous code example:
+
+    var a = 1
+    print a;
+
+Corrected:
+
+    var a = 1;
+    print a;",
+            ErrorCode::ExpectedLeftParen => "\
+The conditions of 'if', 'while' and 'for', and the parameter list of a function
+or method, must be written inside parentheses.
+
+Erroneous code example:
+
+    if ready print \"go\";
+
+Corrected:
+
+    if (ready) print \"go\";",
+            ErrorCode::ExpectedLeftBrace => "\
+Function, method and class bodies must be blocks wrapped in '{' and '}'.
+
+Erroneous code example:
+
+    fun greet() print \"hi\";
+
+Corrected:
+
+    fun greet() { print \"hi\"; }",
+            ErrorCode::ExpectedIdentifier => "\
+A name was required here: after 'var', 'fun' or 'class', in a parameter list,
+after '.', or after 'super.'. Keywords can't be used as names.
+
+Erroneous code example:
+
+    var class = 1;
+
+Corrected:
+
+    var kind = 1;",
+            ErrorCode::InvalidAssignmentTarget => "\
+The left-hand side of '=' must be a variable or an object field.
+
+Erroneous code example:
+
+    1 + a = 3;
+
+Corrected:
+
+    a = 3 - 1;",
+            ErrorCode::TooManyArguments => "\
+Functions can take at most 255 parameters, and calls can pass at most 255
+arguments.
+
+Erroneous code example:
+
+    fun f(a1, a2, /* ... */ a256) {}
+
+Corrected:
+
+    fun f(values) {}",
+            ErrorCode::ExpectedEndOfExpression => "\
+The REPL accepts a bare expression without a trailing ';', but something followed
+the expression that is not part of it.
+
+Erroneous code example:
+
+    > 1 2
+
+Corrected:
+
+    > 1 + 2",
+            ErrorCode::ExpectedDot => "\
+'super' can only be used to access a method of the superclass, so it must be
+followed by '.' and a method name.
+
+Erroneous code example:
+
+    super();
+
+Corrected:
+
+    super.init();",
+            ErrorCode::ReadInOwnInitializer => "\
+A local variable was used in its own initializer. Inside the initializer the new
+variable is declared but not yet defined, so the outer variable of the same name
+is not visible either.
+
+Erroneous code example:
+
+    var a = 1;
+    {
+      var a = a + 1;
+    }
+
+Corrected:
+
+    var a = 1;
+    {
+      var b = a + 1;
+    }",
+            ErrorCode::AlreadyDeclared => "\
+A local scope declared the same name twice. Only global variables may be
+redeclared.
+
+Erroneous code example:
+
+    {
+      var a = 1;
+      var a = 2;
+    }
+
+Corrected:
+
+    {
+      var a = 1;
+      a = 2;
+    }",
+            ErrorCode::TopLevelReturn => "\
+'return' can only appear inside a function or method body.
+
+Erroneous code example:
+
+    return 1;
+
+Corrected:
+
+    fun one() {
+      return 1;
+    }",
+            ErrorCode::ReturnFromInitializer => "\
+An 'init' method always returns the new instance, so it can't return another
+value. A bare 'return;' is allowed to exit early.
+
+Erroneous code example:
+
+    class Point {
+      init(x) { return x; }
+    }
+
+Corrected:
+
+    class Point {
+      init(x) { this.x = x; }
+    }",
+            ErrorCode::ThisOutsideClass => "\
+'this' refers to the instance a method was called on, so it can only be used
+inside a method.
+
+Erroneous code example:
+
+    fun name() { return this.name; }
+
+Corrected:
+
+    class Person {
+      name() { return this.name; }
+    }",
+            ErrorCode::SuperOutsideClass => "\
+'super' refers to the superclass of the enclosing class, so it can only be used
+inside a method.
+
+Erroneous code example:
+
+    super.cook();
+
+Corrected:
+
+    class Brunch < Breakfast {
+      cook() { super.cook(); }
+    }",
+            ErrorCode::SuperWithoutSuperclass => "\
+'super' was used in a class that does not inherit from anything.
+
+Erroneous code example:
+
+    class Breakfast {
+      cook() { super.cook(); }
+    }
+
+Corrected:
+
+    class Breakfast < Meal {
+      cook() { super.cook(); }
+    }",
+            ErrorCode::InheritFromSelf => "\
+A class named itself as its superclass.
+
+Erroneous code example:
+
+    class Oops < Oops {}
+
+Corrected:
+
+    class Oops {}",
+            ErrorCode::OperandMustBeNumber => "\
+Unary '-' negates a number; it can't be applied to any other value.
+
+Erroneous code example:
+
+    print -\"three\";
+
+Corrected:
+
+    print -3;",
+            ErrorCode::OperandsMustBeNumbersOrStrings => "\
+'+' adds two numbers or concatenates two strings; it can't mix the two.
+
+Erroneous code example:
+
+    print \"total: \" + 3;
+
+Corrected:
+
+    print \"total: 3\";",
+            ErrorCode::UndefinedVariable => "\
+A variable was read or assigned before being declared with 'var'.
+
+Erroneous code example:
+
+    count = 1;
+
+Corrected:
+
+    var count = 1;",
+            ErrorCode::NotCallable => "\
+Only functions, methods and classes can be called.
+
+Erroneous code example:
+
+    var name = \"lox\";
+    name();
+
+Corrected:
+
+    fun name() { return \"lox\"; }
+    name();",
+            ErrorCode::ArityMismatch => "\
+A function, method or class initializer was called with a different number of
+arguments than it declares parameters.
+
+Erroneous code example:
+
+    fun add(a, b) { return a + b; }
+    add(1);
+
+Corrected:
+
+    fun add(a, b) { return a + b; }
+    add(1, 2);",
+            ErrorCode::OnlyInstancesHaveProperties => "\
+Properties can only be read from class instances.
+
+Erroneous code example:
+
+    var n = 3;
+    print n.size;
+
+Corrected:
+
+    class Box { init() { this.size = 3; } }
+    var n = Box();
+    print n.size;",
+            ErrorCode::UndefinedProperty => "\
+The instance has no field and its class has no method with this name.
+
+Erroneous code example:
+
+    class Box {}
+    print Box().size;
+
+Corrected:
+
+    class Box {}
+    var box = Box();
+    box.size = 1;
+    print box.size;",
+            ErrorCode::SuperclassMustBeClass => "\
+The name after '<' in a class declaration must refer to a class.
+
+Erroneous code example:
+
+    var Base = \"base\";
+    class Derived < Base {}
+
+Corrected:
+
+    class Base {}
+    class Derived < Base {}",
+            ErrorCode::StackOverflow => "\
+Calls nested more deeply than Lox allows, which almost always means
+a recursive function that never reaches its base case.
+
+Erroneous code example:
+
+    fun count(n) { return count(n + 1); }
+    count(0);
+
+Corrected:
+
+    fun count(n) { if (n == 10) return n; return count(n + 1); }
+    count(0);",
+            ErrorCode::OperandsMustBeNumbers => "\
+The binary operators '-', '*', '/', '<', '<=', '>' and '>=' only work on two
+numbers. Only '+' also accepts strings, and only '==' and '!=' compare any values.
+
+Erroneous code example:
+
+    print \"10\" * 2;
+
+Corrected:
+
+    print 10 * 2;",
+            ErrorCode::OnlyInstancesHaveFields => "\
+Fields can only be assigned on class instances.
+
+Erroneous code example:
+
+    var n = 3;
+    n.size = 1;
+
+Corrected:
+
+    class Box {}
+    var n = Box();
+    n.size = 1;",
+            ErrorCode::CompilerLimit => "\
+The bytecode compiler encodes operands in fixed-size fields, which limits a
+single function to 65536 constants, 256 local variables in scope at once and
+256 captured variables, and a jump to 65535 bytes of code. Split the function
+into smaller ones.
+
+Erroneous code example:
+
+    fun big() {
+        var v0 = 0;
+        // ... 300 more local variables ...
+    }
+
+Corrected:
+
+    fun part1() { var v0 = 0; /* ... */ }
+    fun part2() { /* ... */ }",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_unique_and_parse() {
+        let mut seen = std::collections::HashSet::new();
+        for code in ErrorCode::ALL {
+            assert!(seen.insert(code.as_str()), "duplicate code {code}");
+            assert_eq!(ErrorCode::parse(code.as_str()), Some(code));
+            assert!(code.explanation().contains("Corrected:"), "{code} has no corrected example");
+        }
+        assert_eq!(ErrorCode::parse("l0102"), Some(ErrorCode::ExpectedRightParen));
+        assert_eq!(ErrorCode::parse("L9999"), None);
+    }
+}
@@ -0,0 +1,615 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::ast::*;
+use super::chunk::{Chunk, Constant, Function, OpCode, UpvalueDescriptor};
+use super::errors::{CompileError, LoxError};
+use super::span::{SourceMap, Span};
+use super::symbol::Symbol;
+use super::token::{Literal, Token};
+use super::token_type::TokenType;
+
+/// Locals and upvalues are addressed by a `u8` operand.
+const MAX_LOCALS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+struct Local {
+    name: Symbol,
+    /// Scope depth, or `None` while the variable's initializer is being compiled.
+    depth: Option<usize>,
+    is_captured: bool,
+}
+
+/// A function being compiled. Its locals mirror the stack slots of its frame.
+struct FunctionState {
+    function: Function,
+    kind: FunctionKind,
+    locals: Vec<Local>,
+    scope_depth: usize,
+}
+
+impl FunctionState {
+    fn new(function: Function, kind: FunctionKind) -> Self {
+        // Slot 0 holds the closure being called, or the receiver in a method.
+        let receiver = match kind {
+            FunctionKind::Method | FunctionKind::Initializer => Symbol::THIS,
+            FunctionKind::Script | FunctionKind::Function => Symbol::intern(""),
+        };
+        Self {
+            function,
+            kind,
+            locals: vec![Local { name: receiver, depth: Some(0), is_captured: false }],
+            scope_depth: if kind == FunctionKind::Script { 0 } else { 1 },
+        }
+    }
+}
+
+/// Compiles a resolved AST into bytecode for the [`Vm`](crate::vm::Vm). Local
+/// variables live in stack slots; variables captured by closures become upvalues,
+/// as in clox. The program must have passed the resolver, which reports the
+/// static errors this pass relies on never seeing.
+pub struct Compiler<'a> {
+    source_map: &'a SourceMap,
+    /// The function being compiled and every function enclosing it, innermost last.
+    states: RefCell<Vec<FunctionState>>,
+    errors: RefCell<Vec<CompileError>>,
+}
+
+impl<'a> Compiler<'a> {
+    pub fn new(source_map: &'a SourceMap) -> Self {
+        Self {
+            source_map,
+            states: RefCell::new(Vec::new()),
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Compiles `statements` into the body of a script function.
+    pub fn compile(&self, statements: &[Stmt]) -> Result<Rc<Function>, Vec<CompileError>> {
+        self.states.replace(vec![FunctionState::new(Function::new("", 0), FunctionKind::Script)]);
+        for statement in statements {
+            statement.accept(self).expect("the compiler reports problems as CompileErrors");
+        }
+        let line = statements.last().map_or(1, |statement| self.line(statement.span()));
+        self.emit_return(line);
+        let state = self.states.borrow_mut().pop().expect("the script state is still there");
+        let errors = self.errors.take();
+        if errors.is_empty() {
+            Ok(Rc::new(state.function))
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn line(&self, span: Span) -> u64 {
+        self.source_map.location(span.start).line as u64
+    }
+
+    fn error(&self, line: u64, span: Span, message: &'static str) {
+        self.errors.borrow_mut().push(CompileError::LimitExceeded(line, span, message));
+    }
+
+    fn with_chunk<T>(&self, f: impl FnOnce(&mut Chunk) -> T) -> T {
+        f(&mut self.states.borrow_mut().last_mut().expect("compiling inside a function").function.chunk)
+    }
+
+    fn code_len(&self) -> usize {
+        self.with_chunk(|chunk| chunk.code.len())
+    }
+
+    fn emit(&self, op: OpCode, line: u64) {
+        self.with_chunk(|chunk| chunk.write_op(op, line));
+    }
+
+    fn emit_at(&self, op: OpCode, token: &Token) {
+        self.with_chunk(|chunk| chunk.write_op_at(op, token));
+    }
+
+    fn emit_byte(&self, byte: u8) {
+        self.with_chunk(|chunk| chunk.write_byte(byte));
+    }
+
+    fn emit_u16(&self, value: u16) {
+        self.with_chunk(|chunk| chunk.write_u16(value));
+    }
+
+    fn constant(&self, constant: Constant, span: Span) -> u16 {
+        self.with_chunk(|chunk| chunk.add_constant(constant)).unwrap_or_else(|| {
+            self.error(self.line(span), span, "Too many constants in one chunk.");
+            0
+        })
+    }
+
+    fn name_constant(&self, name: &Token) -> u16 {
+        self.constant(Constant::Str(name.lexeme.symbol()), name.span)
+    }
+
+    fn emit_return(&self, line: u64) {
+        if self.kind() == FunctionKind::Initializer {
+            self.emit(OpCode::GetLocal, line);
+            self.emit_byte(0);
+        } else {
+            self.emit(OpCode::Nil, line);
+        }
+        self.emit(OpCode::Return, line);
+    }
+
+    /// Emits a forward jump and returns the offset of its operand, to be patched
+    /// once the target is known.
+    fn emit_jump(&self, op: OpCode, line: u64) -> usize {
+        self.emit(op, line);
+        self.emit_u16(u16::MAX);
+        self.code_len() - 2
+    }
+
+    /// Points the jump whose operand is at `offset` to the next instruction.
+    fn patch_jump(&self, offset: usize, span: Span) {
+        let distance = self.code_len() - offset - 2;
+        match u16::try_from(distance) {
+            Ok(distance) => self.with_chunk(|chunk| chunk.patch_u16(offset, distance)),
+            Err(_) => self.error(self.line(span), span, "Too much code to jump over."),
+        }
+    }
+
+    fn emit_loop(&self, loop_start: usize, span: Span) {
+        let line = self.line(span);
+        self.emit(OpCode::Loop, line);
+        let distance = self.code_len() - loop_start + 2;
+        match u16::try_from(distance) {
+            Ok(distance) => self.emit_u16(distance),
+            Err(_) => {
+                self.error(line, span, "Loop body too large.");
+                self.emit_u16(0);
+            },
+        }
+    }
+
+    fn kind(&self) -> FunctionKind {
+        self.states.borrow().last().expect("compiling inside a function").kind
+    }
+
+    fn scope_depth(&self) -> usize {
+        self.states.borrow().last().expect("compiling inside a function").scope_depth
+    }
+
+    fn begin_scope(&self) {
+        self.states.borrow_mut().last_mut().expect("compiling inside a function").scope_depth += 1;
+    }
+
+    /// Pops the locals of the innermost scope, moving captured ones to the heap.
+    fn end_scope(&self, line: u64) {
+        let captured: Vec<bool> = {
+            let mut states = self.states.borrow_mut();
+            let state = states.last_mut().expect("compiling inside a function");
+            state.scope_depth -= 1;
+            let depth = state.scope_depth;
+            let keep = state.locals.iter().take_while(|local| local.depth.is_none_or(|d| d <= depth)).count();
+            state.locals.drain(keep..).rev().map(|local| local.is_captured).collect()
+        };
+        for is_captured in captured {
+            self.emit(if is_captured { OpCode::CloseUpvalue } else { OpCode::Pop }, line);
+        }
+    }
+
+    fn add_local(&self, name: Symbol, token: &Token) {
+        let mut states = self.states.borrow_mut();
+        let state = states.last_mut().expect("compiling inside a function");
+        if state.locals.len() == MAX_LOCALS {
+            drop(states);
+            self.error(token.line, token.span, "Too many local variables in function.");
+            return;
+        }
+        state.locals.push(Local { name, depth: None, is_captured: false });
+    }
+
+    /// Declares a local for `name` when inside a scope; globals need no declaration.
+    fn declare_variable(&self, name: &Token) {
+        if self.scope_depth() > 0 {
+            self.add_local(name.lexeme.symbol(), name);
+        }
+    }
+
+    fn mark_initialized(&self) {
+        let mut states = self.states.borrow_mut();
+        let state = states.last_mut().expect("compiling inside a function");
+        let depth = state.scope_depth;
+        if let Some(local) = state.locals.last_mut() {
+            local.depth = Some(depth);
+        }
+    }
+
+    /// Completes a declaration whose value is on top of the stack.
+    fn define_variable(&self, name: &Token) {
+        if self.scope_depth() > 0 {
+            self.mark_initialized();
+        } else {
+            let constant = self.name_constant(name);
+            self.emit(OpCode::DefineGlobal, name.line);
+            self.emit_u16(constant);
+        }
+    }
+
+    fn resolve_local(state: &FunctionState, name: Symbol) -> Option<u8> {
+        state.locals.iter().rposition(|local| local.name == name).map(|slot| slot as u8)
+    }
+
+    /// Finds `name` in the functions enclosing `states[index]`, threading it through
+    /// the upvalues of every function in between.
+    fn resolve_upvalue(&self, states: &mut [FunctionState], index: usize, name: Symbol, token: &Token) -> Option<u8> {
+        if index == 0 {
+            return None;
+        }
+        if let Some(slot) = Self::resolve_local(&states[index - 1], name) {
+            states[index - 1].locals[slot as usize].is_captured = true;
+            return self.add_upvalue(&mut states[index], UpvalueDescriptor { is_local: true, index: slot }, token);
+        }
+        let upvalue = self.resolve_upvalue(states, index - 1, name, token)?;
+        self.add_upvalue(&mut states[index], UpvalueDescriptor { is_local: false, index: upvalue }, token)
+    }
+
+    fn add_upvalue(&self, state: &mut FunctionState, descriptor: UpvalueDescriptor, token: &Token) -> Option<u8> {
+        let upvalues = &mut state.function.upvalues;
+        if let Some(existing) = upvalues.iter().position(|upvalue| *upvalue == descriptor) {
+            return Some(existing as u8);
+        }
+        if upvalues.len() == MAX_LOCALS {
+            self.error(token.line, token.span, "Too many closure variables in function.");
+            return None;
+        }
+        upvalues.push(descriptor);
+        Some((upvalues.len() - 1) as u8)
+    }
+
+    /// Emits a read of variable `name`, or a write of the value on top of the stack
+    /// if `set`. Errors for an undefined global are reported at `token`.
+    fn variable(&self, name: Symbol, token: &Token, set: bool) {
+        let (local, upvalue) = {
+            let mut states = self.states.borrow_mut();
+            let current = states.len() - 1;
+            match Self::resolve_local(&states[current], name) {
+                Some(slot) => (Some(slot), None),
+                None => (None, self.resolve_upvalue(&mut states, current, name, token)),
+            }
+        };
+        if let Some(slot) = local {
+            self.emit(if set { OpCode::SetLocal } else { OpCode::GetLocal }, token.line);
+            self.emit_byte(slot);
+        } else if let Some(index) = upvalue {
+            self.emit(if set { OpCode::SetUpvalue } else { OpCode::GetUpvalue }, token.line);
+            self.emit_byte(index);
+        } else {
+            let constant = self.constant(Constant::Str(name), token.span);
+            self.emit_at(if set { OpCode::SetGlobal } else { OpCode::GetGlobal }, token);
+            self.emit_u16(constant);
+        }
+    }
+
+    /// Compiles a function body and emits the closure that creates it.
+    fn function(&self, stmt: &FunctionStmt, kind: FunctionKind) {
+        let function = Function::new(&stmt.name.lexeme, stmt.params.len());
+        self.states.borrow_mut().push(FunctionState::new(function, kind));
+        for param in &stmt.params {
+            self.add_local(param.lexeme.symbol(), param);
+            self.mark_initialized();
+        }
+        for statement in stmt.body.iter() {
+            statement.accept(self).expect("the compiler reports problems as CompileErrors");
+        }
+        self.emit_return(self.line(Span::new(stmt.span.end.saturating_sub(1), stmt.span.end)));
+        let state = self.states.borrow_mut().pop().expect("the function state is still there");
+
+        let constant = self.constant(Constant::Function(Rc::new(state.function)), stmt.span);
+        self.emit(OpCode::Closure, stmt.name.line);
+        self.emit_u16(constant);
+    }
+
+    fn expr(&self, expr: &Expr) -> Result<(), LoxError> {
+        expr.accept(self)
+    }
+
+    fn stmt(&self, stmt: &Stmt) -> Result<(), LoxError> {
+        stmt.accept(self)
+    }
+}
+
+impl Visitor<()> for Compiler<'_> {
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<(), LoxError> {
+        self.expr(&expr.left)?;
+        self.expr(&expr.right)?;
+        let op = match expr.operator.token_type {
+            TokenType::EqualEqual => OpCode::Equal,
+            TokenType::BangEqual => OpCode::NotEqual,
+            TokenType::Greater => OpCode::Greater,
+            TokenType::GreaterEqual => OpCode::GreaterEqual,
+            TokenType::Less => OpCode::Less,
+            TokenType::LessEqual => OpCode::LessEqual,
+            TokenType::Plus => OpCode::Add,
+            TokenType::Minus => OpCode::Subtract,
+            TokenType::Star => OpCode::Multiply,
+            TokenType::Slash => OpCode::Divide,
+            _ => return Err(LoxError::InvalidOperator(expr.operator.clone())),
+        };
+        self.emit_at(op, &expr.operator);
+        Ok(())
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<(), LoxError> {
+        self.expr(&expr.expression)
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<(), LoxError> {
+        let line = self.line(expr.span);
+        let constant = match &expr.value {
+            None => Err(OpCode::Nil),
+            Some(Literal::True) => Err(OpCode::True),
+            Some(Literal::False) => Err(OpCode::False),
+            Some(Literal::Number(n)) => Ok(Constant::Number(*n)),
+            Some(Literal::Str(s)) | Some(Literal::Identifier(s)) => Ok(Constant::Str(*s)),
+        };
+        match constant {
+            Ok(constant) => {
+                let index = self.constant(constant, expr.span);
+                self.emit(OpCode::Constant, line);
+                self.emit_u16(index);
+            },
+            Err(op) => self.emit(op, line),
+        }
+        Ok(())
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<(), LoxError> {
+        self.expr(&expr.right)?;
+        match expr.operator.token_type {
+            TokenType::Bang => self.emit(OpCode::Not, expr.operator.line),
+            TokenType::Minus => self.emit_at(OpCode::Negate, &expr.operator),
+            _ => return Err(LoxError::InvalidOperator(expr.operator.clone())),
+        }
+        Ok(())
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<(), LoxError> {
+        self.variable(expr.name.lexeme.symbol(), &expr.name, false);
+        Ok(())
+    }
+
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<(), LoxError> {
+        self.expr(&expr.value)?;
+        self.variable(expr.name.lexeme.symbol(), &expr.name, true);
+        Ok(())
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<(), LoxError> {
+        self.expr(&expr.left)?;
+        let line = expr.operator.line;
+        if expr.operator.token_type == TokenType::Or {
+            let else_jump = self.emit_jump(OpCode::JumpIfFalse, line);
+            let end_jump = self.emit_jump(OpCode::Jump, line);
+            self.patch_jump(else_jump, expr.span);
+            self.emit(OpCode::Pop, line);
+            self.expr(&expr.right)?;
+            self.patch_jump(end_jump, expr.span);
+        } else {
+            let end_jump = self.emit_jump(OpCode::JumpIfFalse, line);
+            self.emit(OpCode::Pop, line);
+            self.expr(&expr.right)?;
+            self.patch_jump(end_jump, expr.span);
+        }
+        Ok(())
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<(), LoxError> {
+        self.expr(&expr.callee)?;
+        for argument in &expr.arguments {
+            self.expr(argument)?;
+        }
+        self.emit_at(OpCode::Call, &expr.paren);
+        // The parser rejects calls with more than 255 arguments.
+        self.emit_byte(expr.arguments.len() as u8);
+        Ok(())
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<(), LoxError> {
+        self.expr(&expr.object)?;
+        let name = self.name_constant(&expr.name);
+        self.emit_at(OpCode::GetProperty, &expr.name);
+        self.emit_u16(name);
+        Ok(())
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<(), LoxError> {
+        self.expr(&expr.object)?;
+        self.expr(&expr.value)?;
+        let name = self.name_constant(&expr.name);
+        self.emit_at(OpCode::SetProperty, &expr.name);
+        self.emit_u16(name);
+        Ok(())
+    }
+
+    fn visit_this_expr(&self, expr: &ThisExpr) -> Result<(), LoxError> {
+        self.variable(Symbol::THIS, &expr.keyword, false);
+        Ok(())
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<(), LoxError> {
+        self.variable(Symbol::THIS, &expr.keyword, false);
+        self.variable(Symbol::SUPER, &expr.keyword, false);
+        let name = self.name_constant(&expr.method);
+        self.emit_at(OpCode::GetSuper, &expr.method);
+        self.emit_u16(name);
+        Ok(())
+    }
+}
+
+impl StmtVisitor<()> for Compiler<'_> {
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<(), LoxError> {
+        self.expr(&stmt.expression)?;
+        self.emit(OpCode::Pop, self.line(stmt.span));
+        Ok(())
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<(), LoxError> {
+        self.expr(&stmt.expression)?;
+        self.emit(OpCode::Print, self.line(stmt.span));
+        Ok(())
+    }
+
+    fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<(), LoxError> {
+        self.declare_variable(&stmt.name);
+        match &stmt.initializer {
+            Some(initializer) => self.expr(initializer)?,
+            None => self.emit(OpCode::Nil, stmt.name.line),
+        }
+        self.define_variable(&stmt.name);
+        Ok(())
+    }
+
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<(), LoxError> {
+        self.begin_scope();
+        for statement in &stmt.statements {
+            self.stmt(statement)?;
+        }
+        self.end_scope(self.line(Span::new(stmt.span.end.saturating_sub(1), stmt.span.end)));
+        Ok(())
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<(), LoxError> {
+        let line = self.line(stmt.span);
+        self.expr(&stmt.condition)?;
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse, line);
+        self.emit(OpCode::Pop, line);
+        self.stmt(&stmt.then_branch)?;
+        let else_jump = self.emit_jump(OpCode::Jump, line);
+        self.patch_jump(then_jump, stmt.span);
+        self.emit(OpCode::Pop, line);
+        if let Some(else_branch) = &stmt.else_branch {
+            self.stmt(else_branch)?;
+        }
+        self.patch_jump(else_jump, stmt.span);
+        Ok(())
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<(), LoxError> {
+        let line = self.line(stmt.span);
+        let loop_start = self.code_len();
+        self.expr(&stmt.condition)?;
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse, line);
+        self.emit(OpCode::Pop, line);
+        self.stmt(&stmt.body)?;
+        self.emit_loop(loop_start, stmt.span);
+        self.patch_jump(exit_jump, stmt.span);
+        self.emit(OpCode::Pop, line);
+        Ok(())
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<(), LoxError> {
+        // Marked initialized before the body so that the function can call itself.
+        self.declare_variable(&stmt.name);
+        if self.scope_depth() > 0 {
+            self.mark_initialized();
+        }
+        self.function(stmt, FunctionKind::Function);
+        self.define_variable(&stmt.name);
+        Ok(())
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<(), LoxError> {
+        match &stmt.value {
+            Some(value) => {
+                self.expr(value)?;
+                self.emit(OpCode::Return, stmt.keyword.line);
+            },
+            None => self.emit_return(stmt.keyword.line),
+        }
+        Ok(())
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<(), LoxError> {
+        let line = stmt.name.line;
+        let name = self.name_constant(&stmt.name);
+        self.declare_variable(&stmt.name);
+        self.emit(OpCode::Class, line);
+        self.emit_u16(name);
+        self.define_variable(&stmt.name);
+
+        if let Some(superclass) = &stmt.superclass {
+            self.variable(superclass.name.lexeme.symbol(), &superclass.name, false);
+            // `super` is a local of a scope wrapping the methods, which capture it.
+            self.begin_scope();
+            self.add_local(Symbol::SUPER, &superclass.name);
+            self.mark_initialized();
+            self.variable(stmt.name.lexeme.symbol(), &stmt.name, false);
+            self.emit_at(OpCode::Inherit, &superclass.name);
+        }
+
+        self.variable(stmt.name.lexeme.symbol(), &stmt.name, false);
+        for method in &stmt.methods {
+            let kind = if method.name.lexeme.symbol() == Symbol::INIT { FunctionKind::Initializer } else { FunctionKind::Method };
+            self.function(method, kind);
+            let name = self.name_constant(&method.name);
+            self.emit(OpCode::Method, method.name.line);
+            self.emit_u16(name);
+        }
+        self.emit(OpCode::Pop, line);
+
+        if stmt.superclass.is_some() {
+            self.end_scope(line);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn compile(source: &str) -> Rc<Function> {
+        let statements = parse(source.as_bytes()).unwrap();
+        Compiler::new(&SourceMap::new(source.as_bytes())).compile(&statements).unwrap()
+    }
+
+    #[test]
+    fn test_expression_bytecode() {
+        let script = compile("print 1 + 2 * 1;");
+        assert_eq!(script.chunk.constants, [Constant::Number(1.0), Constant::Number(2.0)]);
+        assert_eq!(script.chunk.code, [
+            OpCode::Constant as u8, 0, 0,
+            OpCode::Constant as u8, 0, 1,
+            OpCode::Constant as u8, 0, 0,
+            OpCode::Multiply as u8,
+            OpCode::Add as u8,
+            OpCode::Print as u8,
+            OpCode::Nil as u8,
+            OpCode::Return as u8,
+        ]);
+        assert_eq!(script.chunk.site(9).map(|token| token.lexeme.as_str()), Some("*"));
+    }
+
+    #[test]
+    fn test_closures_capture_upvalues() {
+        let script = compile("fun outer() {\n  var x = 1;\n  fun middle() {\n    fun inner() { return x; }\n  }\n}");
+        let Constant::Function(outer) = &script.chunk.constants[0] else { panic!("expected a function") };
+        let Constant::Function(middle) = &outer.chunk.constants[1] else { panic!("expected a function") };
+        let Constant::Function(inner) = &middle.chunk.constants[0] else { panic!("expected a function") };
+        assert_eq!(middle.upvalues, [UpvalueDescriptor { is_local: true, index: 1 }]);
+        assert_eq!(inner.upvalues, [UpvalueDescriptor { is_local: false, index: 0 }]);
+        assert_eq!(inner.chunk.line(0), 4);
+    }
+
+    #[test]
+    fn test_too_many_locals() {
+        let locals: String = (0..300).map(|i| format!("var v{i};")).collect();
+        let source = format!("{{ {locals} }}");
+        let statements = parse(source.as_bytes()).unwrap();
+        let errors = Compiler::new(&SourceMap::new(source.as_bytes())).compile(&statements).unwrap_err();
+        assert_eq!(errors.len(), 300 - 255);
+        assert_eq!(errors[0].to_string(), "[line 1] Error: Too many local variables in function.");
+    }
+}
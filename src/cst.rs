@@ -0,0 +1,361 @@
+use std::fmt;
+use std::rc::Rc;
+
+use super::span::Span;
+use super::token_type::TokenType;
+
+/// Kinds of interior nodes in the concrete syntax tree. Leaves are tokens and keep
+/// their `TokenType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    SourceFile,
+    ClassDecl,
+    /// A `fun` declaration, or a method inside a class body.
+    Function,
+    ParamList,
+    VarDecl,
+    ExprStmt,
+    PrintStmt,
+    ReturnStmt,
+    IfStmt,
+    WhileStmt,
+    ForStmt,
+    Block,
+    /// Arithmetic, comparison and logical (`and`/`or`) operators alike.
+    BinaryExpr,
+    UnaryExpr,
+    GroupingExpr,
+    LiteralExpr,
+    NameExpr,
+    /// Assignment to a variable or to a property.
+    AssignExpr,
+    CallExpr,
+    ArgList,
+    GetExpr,
+    ThisExpr,
+    SuperExpr,
+    /// Tokens the parser could not fit anywhere.
+    Error,
+}
+
+/// Immutable leaf of the green tree. It knows its text but not its position, so
+/// identical tokens can be shared. The text is the raw source bytes, which need not
+/// be valid UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: TokenType,
+    text: Vec<u8>,
+}
+
+impl GreenToken {
+    pub fn new(kind: TokenType, text: Vec<u8>) -> Self {
+        Self {
+            kind,
+            text,
+        }
+    }
+
+    pub fn kind(&self) -> &TokenType {
+        &self.kind
+    }
+
+    pub fn text(&self) -> &[u8] {
+        &self.text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len(),
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Immutable interior node of the green tree. Like `GreenToken` it stores only its
+/// length in bytes; absolute offsets live in the red `SyntaxNode` wrapping it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: NodeKind,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: NodeKind, children: Vec<GreenElement>) -> Self {
+        let len = children.iter().map(GreenElement::len).sum();
+        Self {
+            kind,
+            len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    fn write_text(&self, out: &mut Vec<u8>) {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.write_text(out),
+                GreenElement::Token(token) => out.extend_from_slice(&token.text),
+            }
+        }
+    }
+}
+
+/// Position in a `GreenNodeBuilder` where a node may later be started, so that a
+/// left operand already built can be wrapped once its operator is seen.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint(usize);
+
+/// Builds a green tree bottom-up from a flat sequence of start, token and finish
+/// events.
+#[derive(Debug, Default)]
+pub struct GreenNodeBuilder {
+    parents: Vec<(NodeKind, usize)>,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNodeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start_node(&mut self, kind: NodeKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    pub fn token(&mut self, kind: TokenType, text: &[u8]) {
+        self.children.push(GreenElement::Token(Rc::new(GreenToken::new(kind, text.to_vec()))));
+    }
+
+    pub fn finish_node(&mut self) {
+        let (kind, first_child) = self.parents.pop().expect("finish_node without start_node");
+        let children = self.children.split_off(first_child);
+        self.children.push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.children.len())
+    }
+
+    /// Starts a node whose first child is whatever was built since `checkpoint`.
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: NodeKind) {
+        let Checkpoint(first_child) = checkpoint;
+        assert!(first_child <= self.children.len(), "checkpoint is no longer valid");
+        if let Some(&(_, parent_start)) = self.parents.last() {
+            assert!(first_child >= parent_start, "checkpoint is outside the current node");
+        }
+        self.parents.push((kind, first_child));
+    }
+
+    /// Returns the single root node built.
+    pub fn finish(mut self) -> Rc<GreenNode> {
+        assert!(self.parents.is_empty(), "unfinished nodes");
+        assert_eq!(self.children.len(), 1, "expected exactly one root node");
+        match self.children.pop() {
+            Some(GreenElement::Node(node)) => node,
+            _ => panic!("the root must be a node"),
+        }
+    }
+}
+
+/// Red view of a green node: adds the absolute offset and a link to the parent, so
+/// tools can navigate the tree in both directions. Cloning is cheap.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.0.offset, self.0.offset + self.0.green.len)
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        self.0.green.children.iter().map(|child| {
+            let element = match child {
+                GreenElement::Node(node) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: Rc::clone(node),
+                    offset,
+                    parent: Some(self.clone()),
+                }))),
+                GreenElement::Token(token) => SyntaxElement::Token(SyntaxToken {
+                    green: Rc::clone(token),
+                    offset,
+                    parent: self.clone(),
+                }),
+            };
+            offset += child.len();
+            element
+        }).collect()
+    }
+
+    /// Child nodes, skipping tokens.
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens().into_iter().filter_map(|element| match element {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        }).collect()
+    }
+
+    /// Every token under this node in source order, trivia included.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for element in self.children_with_tokens() {
+            match element {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// Exact source bytes covered by this node.
+    pub fn text(&self) -> Vec<u8> {
+        let mut text = Vec::with_capacity(self.0.green.len);
+        self.0.green.write_text(&mut text);
+        text
+    }
+
+    fn write_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(f, "{:indent$}{:?}@{}..{}", "", self.kind(), self.span().start, self.span().end, indent = depth * 2)?;
+        for element in self.children_with_tokens() {
+            match element {
+                SyntaxElement::Node(node) => node.write_tree(f, depth + 1)?,
+                SyntaxElement::Token(token) => writeln!(f, "{:indent$}{:?}", "", token, indent = (depth + 1) * 2)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> &TokenType {
+        &self.green.kind
+    }
+
+    pub fn text(&self) -> &[u8] {
+        &self.green.text
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.offset, self.offset + self.green.text.len())
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+}
+
+/// Prints the source text, so that `format!("{}", root)` is the original file if it
+/// is valid UTF-8.
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.text()))
+    }
+}
+
+/// Prints the tree one element per line, indented by depth.
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_tree(f, 0)
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{}..{} {:?}", self.kind(), self.span().start, self.span().end, String::from_utf8_lossy(self.text()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_wraps_at_checkpoint() {
+        let mut builder = GreenNodeBuilder::new();
+        builder.start_node(NodeKind::SourceFile);
+        let checkpoint = builder.checkpoint();
+        builder.token(TokenType::Number, b"1");
+        builder.start_node_at(checkpoint, NodeKind::BinaryExpr);
+        builder.token(TokenType::Plus, b"+");
+        builder.token(TokenType::Number, b"2");
+        builder.finish_node();
+        builder.finish_node();
+        let root = SyntaxNode::new_root(builder.finish());
+
+        assert_eq!(root.text(), b"1+2");
+        let binary = &root.children()[0];
+        assert_eq!(binary.kind(), NodeKind::BinaryExpr);
+        assert_eq!(binary.span(), Span::new(0, 3));
+        let plus = &binary.tokens()[1];
+        assert_eq!(plus.span(), Span::new(1, 2));
+        assert_eq!(plus.parent().kind(), NodeKind::BinaryExpr);
+        assert_eq!(plus.parent().parent().map(|node| node.kind()), Some(NodeKind::SourceFile));
+    }
+}
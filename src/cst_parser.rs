@@ -0,0 +1,472 @@
+use crate::cst::{Checkpoint, GreenNodeBuilder, NodeKind, SyntaxNode};
+use crate::scanner::Scanner;
+use crate::token::Token;
+use crate::token_type::TokenType;
+
+/// Binary operators from loosest to tightest binding.
+const BINARY_LEVELS: [&[TokenType]; 6] = [
+    &[TokenType::Or],
+    &[TokenType::And],
+    &[TokenType::BangEqual, TokenType::EqualEqual],
+    &[TokenType::Greater, TokenType::GreaterEqual, TokenType::Less, TokenType::LessEqual],
+    &[TokenType::Minus, TokenType::Plus],
+    &[TokenType::Slash, TokenType::Star],
+];
+
+/// Parses `source` into a lossless concrete syntax tree: every byte, including
+/// whitespace, comments and malformed input, ends up in exactly one token, so the
+/// root's `text()` is the original source. Never fails; diagnostics come from the
+/// regular `Parser`, and whatever does not fit the grammar lands in `Error` nodes.
+pub fn parse_cst(source: &[u8]) -> SyntaxNode {
+    let (tokens, _) = Scanner::new(source.to_vec()).lossless().scan_tokens();
+    let mut parser = CstParser::new(source, tokens);
+    parser.source_file();
+    SyntaxNode::new_root(parser.builder.finish())
+}
+
+struct CstParser<'a> {
+    /// Token text is taken from here by span, so the tree keeps the exact bytes.
+    source: &'a [u8],
+    tokens: Vec<Token>,
+    /// Index of the next token to add to the tree, which may be trivia.
+    current: usize,
+    builder: GreenNodeBuilder,
+}
+
+impl<'a> CstParser<'a> {
+    fn new(source: &'a [u8], tokens: Vec<Token>) -> Self {
+        Self {
+            source,
+            tokens,
+            current: 0,
+            builder: GreenNodeBuilder::new(),
+        }
+    }
+
+    fn source_file(&mut self) {
+        // Not `start`: there is no parent yet to hold leading trivia.
+        self.builder.start_node(NodeKind::SourceFile);
+        self.declarations(|parser| parser.at_end());
+        self.trivia();
+        self.builder.finish_node();
+    }
+
+    /// Parses declarations until `done`. A stray closing token would start nothing
+    /// and stall the loop, so it is wrapped in an `Error` node instead.
+    fn declarations(&mut self, done: impl Fn(&Self) -> bool) {
+        while !done(self) && !self.at_end() {
+            if self.at(TokenType::RightParen) || self.at(TokenType::RightBrace) {
+                self.error_token();
+            } else {
+                self.declaration();
+            }
+        }
+    }
+
+    fn declaration(&mut self) {
+        match self.peek() {
+            TokenType::Class => self.class_declaration(),
+            TokenType::Fun => {
+                self.start(NodeKind::Function);
+                self.bump();
+                self.function_rest();
+                self.finish();
+            },
+            TokenType::Var => self.var_declaration(),
+            _ => self.statement(),
+        }
+    }
+
+    fn class_declaration(&mut self) {
+        self.start(NodeKind::ClassDecl);
+        self.bump();
+        self.expect(TokenType::Identifier);
+        if self.eat(TokenType::Less) {
+            self.expect(TokenType::Identifier);
+        }
+        self.expect(TokenType::LeftBrace);
+        while !self.at(TokenType::RightBrace) && !self.at_end() {
+            if self.at(TokenType::Identifier) {
+                self.start(NodeKind::Function);
+                self.function_rest();
+                self.finish();
+            } else {
+                self.error_token();
+            }
+        }
+        self.expect(TokenType::RightBrace);
+        self.finish();
+    }
+
+    /// Name, parameters and body of a function or method.
+    fn function_rest(&mut self) {
+        self.expect(TokenType::Identifier);
+        self.start(NodeKind::ParamList);
+        self.expect(TokenType::LeftParen);
+        if !self.at(TokenType::RightParen) {
+            loop {
+                self.expect(TokenType::Identifier);
+                if !self.eat(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.expect(TokenType::RightParen);
+        self.finish();
+        self.block();
+    }
+
+    fn var_declaration(&mut self) {
+        self.start(NodeKind::VarDecl);
+        self.bump();
+        self.expect(TokenType::Identifier);
+        if self.eat(TokenType::Equal) {
+            self.expression();
+        }
+        self.expect(TokenType::Semicolon);
+        self.finish();
+    }
+
+    fn statement(&mut self) {
+        match self.peek() {
+            TokenType::For => self.for_statement(),
+            TokenType::If => {
+                self.start(NodeKind::IfStmt);
+                self.bump();
+                self.condition();
+                self.statement();
+                if self.eat(TokenType::Else) {
+                    self.statement();
+                }
+                self.finish();
+            },
+            TokenType::Print => {
+                self.start(NodeKind::PrintStmt);
+                self.bump();
+                self.expression();
+                self.expect(TokenType::Semicolon);
+                self.finish();
+            },
+            TokenType::Return => {
+                self.start(NodeKind::ReturnStmt);
+                self.bump();
+                if !self.at(TokenType::Semicolon) {
+                    self.expression();
+                }
+                self.expect(TokenType::Semicolon);
+                self.finish();
+            },
+            TokenType::While => {
+                self.start(NodeKind::WhileStmt);
+                self.bump();
+                self.condition();
+                self.statement();
+                self.finish();
+            },
+            TokenType::LeftBrace => self.block(),
+            _ => {
+                self.start(NodeKind::ExprStmt);
+                self.expression();
+                self.expect(TokenType::Semicolon);
+                self.finish();
+            },
+        }
+    }
+
+    fn for_statement(&mut self) {
+        self.start(NodeKind::ForStmt);
+        self.bump();
+        self.expect(TokenType::LeftParen);
+        match self.peek() {
+            TokenType::Semicolon => self.bump(),
+            TokenType::Var => self.var_declaration(),
+            _ => {
+                self.start(NodeKind::ExprStmt);
+                self.expression();
+                self.expect(TokenType::Semicolon);
+                self.finish();
+            },
+        }
+        if !self.at(TokenType::Semicolon) {
+            self.expression();
+        }
+        self.expect(TokenType::Semicolon);
+        if !self.at(TokenType::RightParen) {
+            self.expression();
+        }
+        self.expect(TokenType::RightParen);
+        self.statement();
+        self.finish();
+    }
+
+    /// The parenthesized condition of an `if` or `while`.
+    fn condition(&mut self) {
+        self.expect(TokenType::LeftParen);
+        self.expression();
+        self.expect(TokenType::RightParen);
+    }
+
+    fn block(&mut self) {
+        self.start(NodeKind::Block);
+        self.expect(TokenType::LeftBrace);
+        self.declarations(|parser| parser.at(TokenType::RightBrace));
+        self.expect(TokenType::RightBrace);
+        self.finish();
+    }
+
+    fn expression(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.binary(0);
+        if self.at(TokenType::Equal) {
+            self.builder.start_node_at(checkpoint, NodeKind::AssignExpr);
+            self.bump();
+            self.expression();
+            self.finish();
+        }
+    }
+
+    fn binary(&mut self, level: usize) {
+        if level == BINARY_LEVELS.len() {
+            return self.unary();
+        }
+        let checkpoint = self.checkpoint();
+        self.binary(level + 1);
+        while BINARY_LEVELS[level].contains(self.peek()) {
+            self.builder.start_node_at(checkpoint, NodeKind::BinaryExpr);
+            self.bump();
+            self.binary(level + 1);
+            self.finish();
+        }
+    }
+
+    fn unary(&mut self) {
+        if self.at(TokenType::Bang) || self.at(TokenType::Minus) {
+            self.start(NodeKind::UnaryExpr);
+            self.bump();
+            self.unary();
+            self.finish();
+        } else {
+            self.call();
+        }
+    }
+
+    fn call(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.primary();
+        loop {
+            if self.at(TokenType::LeftParen) {
+                self.builder.start_node_at(checkpoint, NodeKind::CallExpr);
+                self.arguments();
+                self.finish();
+            } else if self.at(TokenType::Dot) {
+                self.builder.start_node_at(checkpoint, NodeKind::GetExpr);
+                self.bump();
+                self.expect(TokenType::Identifier);
+                self.finish();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn arguments(&mut self) {
+        self.start(NodeKind::ArgList);
+        self.bump();
+        if !self.at(TokenType::RightParen) {
+            loop {
+                self.expression();
+                if !self.eat(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.expect(TokenType::RightParen);
+        self.finish();
+    }
+
+    fn primary(&mut self) {
+        match self.peek() {
+            TokenType::False | TokenType::True | TokenType::Nil | TokenType::Number | TokenType::String => {
+                self.node(NodeKind::LiteralExpr);
+            },
+            TokenType::Identifier => self.node(NodeKind::NameExpr),
+            TokenType::This => self.node(NodeKind::ThisExpr),
+            TokenType::Super => {
+                self.start(NodeKind::SuperExpr);
+                self.bump();
+                self.expect(TokenType::Dot);
+                self.expect(TokenType::Identifier);
+                self.finish();
+            },
+            TokenType::LeftParen => {
+                self.start(NodeKind::GroupingExpr);
+                self.bump();
+                self.expression();
+                self.expect(TokenType::RightParen);
+                self.finish();
+            },
+            // Leave tokens that close an enclosing construct to their owner; the
+            // expression is simply missing.
+            TokenType::Semicolon | TokenType::RightParen | TokenType::RightBrace | TokenType::Eof => {},
+            _ => self.error_token(),
+        }
+    }
+
+    /// Wraps the next token alone in a node of `kind`.
+    fn node(&mut self, kind: NodeKind) {
+        self.start(kind);
+        self.bump();
+        self.finish();
+    }
+
+    fn error_token(&mut self) {
+        self.node(NodeKind::Error);
+    }
+
+    /// Adds the next token if it is `kind`. A missing token is simply left out of
+    /// the tree.
+    fn expect(&mut self, kind: TokenType) {
+        self.eat(kind);
+    }
+
+    fn eat(&mut self, kind: TokenType) -> bool {
+        if self.at(kind) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn at(&self, kind: TokenType) -> bool {
+        *self.peek() == kind
+    }
+
+    fn at_end(&self) -> bool {
+        self.at(TokenType::Eof)
+    }
+
+    /// Type of the next significant token.
+    fn peek(&self) -> &TokenType {
+        &self.tokens[self.position()].token_type
+    }
+
+    /// Index of the next significant token.
+    fn position(&self) -> usize {
+        let mut position = self.current;
+        while self.tokens[position].token_type.is_trivia() {
+            position += 1;
+        }
+        position
+    }
+
+    /// Adds pending trivia, then the next significant token unless it is `Eof`.
+    fn bump(&mut self) {
+        self.trivia();
+        let token = &self.tokens[self.current];
+        if token.token_type != TokenType::Eof {
+            self.builder.token(token.token_type.clone(), &self.source[token.span.range()]);
+            self.current += 1;
+        }
+    }
+
+    /// Adds pending trivia to the node currently open, so the nodes started after
+    /// it begin at their first significant token.
+    fn trivia(&mut self) {
+        while self.tokens[self.current].token_type.is_trivia() {
+            let token = &self.tokens[self.current];
+            self.builder.token(token.token_type.clone(), &self.source[token.span.range()]);
+            self.current += 1;
+        }
+    }
+
+    fn start(&mut self, kind: NodeKind) {
+        self.trivia();
+        self.builder.start_node(kind);
+    }
+
+    fn checkpoint(&mut self) -> Checkpoint {
+        self.trivia();
+        self.builder.checkpoint()
+    }
+
+    fn finish(&mut self) {
+        self.builder.finish_node();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::Span;
+
+    #[test]
+    fn test_round_trips_source_exactly() {
+        let sources = [
+            "",
+            "  \n\n",
+            "// only a comment",
+            "var a = 1;\n\n// Greeting.\nprint \"hi\" + a; /* trailing */\n",
+            "class B < A {\r\n  init(x) { this.x = x; }\r\n  get() { return super.get(); }\r\n}\r\n",
+            "for (var i = 0; i < 3; i = i + 1) {\n\tprint i;\n}\n",
+            "fun f(a, b) { if (a or !b) return -a; else while (b) b = nil; }",
+            "print \"héllo\"; // ünïcode\n",
+            "var = ;\n}}) print 1 +;\nclass { 1 }\nfun (",
+            "print @ # 1; \"unterminated\n",
+            "/* unterminated block",
+        ];
+        for source in sources {
+            let root = parse_cst(source.as_bytes());
+            assert_eq!(root.text(), source.as_bytes());
+            assert_eq!(root.span().len(), source.len());
+        }
+
+        let source = b"// \xff\xfe\nprint \"\xff\";\n";
+        let root = parse_cst(source);
+        assert_eq!(root.text(), source);
+        assert_eq!(root.span().len(), source.len());
+        let print = &root.children()[0];
+        assert_eq!(print.span(), Span::new(6, 16));
+        assert!(root.tokens().iter().all(|token| &source[token.span().range()] == token.text()));
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let root = parse_cst(b"print 1 + 2 * 3; // six\n");
+        assert_eq!(format!("{:?}", root), "\
+SourceFile@0..24
+  PrintStmt@0..16
+    Print@0..5 \"print\"
+    Whitespace@5..6 \" \"
+    BinaryExpr@6..15
+      LiteralExpr@6..7
+        Number@6..7 \"1\"
+      Whitespace@7..8 \" \"
+      Plus@8..9 \"+\"
+      Whitespace@9..10 \" \"
+      BinaryExpr@10..15
+        LiteralExpr@10..11
+          Number@10..11 \"2\"
+        Whitespace@11..12 \" \"
+        Star@12..13 \"*\"
+        Whitespace@13..14 \" \"
+        LiteralExpr@14..15
+          Number@14..15 \"3\"
+    Semicolon@15..16 \";\"
+  Whitespace@16..17 \" \"
+  Comment@17..23 \"// six\"
+  Whitespace@23..24 \"\\n\"
+");
+    }
+
+    #[test]
+    fn test_unexpected_tokens_become_error_nodes() {
+        let root = parse_cst(b"}\nvar a = b.c = d;");
+        let kinds: Vec<NodeKind> = root.children().iter().map(SyntaxNode::kind).collect();
+        assert_eq!(kinds, vec![NodeKind::Error, NodeKind::VarDecl]);
+        let assign = &root.children()[1].children()[0];
+        assert_eq!(assign.kind(), NodeKind::AssignExpr);
+        assert_eq!(assign.text(), b"b.c = d");
+        assert_eq!(assign.children()[0].kind(), NodeKind::GetExpr);
+    }
+}
@@ -0,0 +1,557 @@
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::codes::ErrorCode;
+use crate::errors::{CompileError, LoxError, ParserError, ResolverError, StaticError, TokenError};
+use crate::span::{SourceMap, Span};
+use crate::token::Token;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A span of source to underline, with an optional message next to the underline.
+/// The primary label marks where the problem is; secondary labels give context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+impl Label {
+    pub fn primary(span: Span, message: &str) -> Self {
+        Self {
+            span,
+            message: message.to_string(),
+            primary: true,
+        }
+    }
+
+    pub fn secondary(span: Span, message: &str) -> Self {
+        Self {
+            span,
+            message: message.to_string(),
+            primary: false,
+        }
+    }
+
+    /// The label's message; secondary labels without one describe the delimiter they
+    /// point at.
+    pub fn text(&self, source: &[u8]) -> String {
+        if !self.message.is_empty() || self.primary {
+            return self.message.clone();
+        }
+        match source.get(self.span.range()) {
+            Some(b"(") => "opening parenthesis here".to_string(),
+            Some(b"{") => "opening brace here".to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// A user-facing report about the source, independent of how it gets printed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<ErrorCode>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: &str) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: None,
+            message: message.to_string(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_code(mut self, code: Option<ErrorCode>) -> Self {
+        self.code = code;
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    /// The span of the first primary label, which is where the diagnostic is reported.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels.iter().find(|label| label.primary).map(|label| label.span)
+    }
+
+    fn at_token(message: &str, token: &Token) -> Self {
+        Self::error(message).with_label(Label::primary(token.span, ""))
+    }
+}
+
+impl From<&TokenError> for Diagnostic {
+    fn from(error: &TokenError) -> Self {
+        let diagnostic = match error {
+            TokenError::InvalidToken(_, c, span) => Diagnostic::error(&format!("Unexpected character \"{c}\"."))
+                .with_label(Label::primary(*span, "not valid in Lox source")),
+            TokenError::UnterminatedString(_, span) => Diagnostic::error("Unterminated string.")
+                .with_label(Label::primary(Span::new(span.start, span.start + 1), "string starts here"))
+                .with_help("add a closing '\"' to end the string"),
+            TokenError::UnterminatedBlockComment(_, span) => Diagnostic::error("Unterminated block comment.")
+                .with_label(Label::primary(Span::new(span.start, span.start + 2), "comment starts here"))
+                .with_help("close the comment with '*/'"),
+            TokenError::InvalidUtf8(_, span) => Diagnostic::error("Invalid UTF-8 in string.")
+                .with_label(Label::primary(*span, "not valid UTF-8"))
+                .with_help("save the file as UTF-8"),
+        };
+        diagnostic.with_code(Some(error.code()))
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Self {
+        let diagnostic = match error {
+            ParserError::At { message, span, related, .. } | ParserError::Eof { message, span, related, .. } => {
+                let diagnostic = Diagnostic::error(message).with_label(Label::primary(*span, ""));
+                match related {
+                    Some(related) => diagnostic.with_label(Label::secondary(*related, "")),
+                    None => diagnostic,
+                }
+            },
+            ParserError::InvalidAssignmentTarget(_, _, span) => Diagnostic::error("Invalid assignment target.")
+                .with_label(Label::primary(*span, "can't assign to the expression on the left"))
+                .with_note("only variables and object fields can be assigned to"),
+        };
+        diagnostic.with_code(Some(error.code()))
+    }
+}
+
+impl From<&ResolverError> for Diagnostic {
+    fn from(error: &ResolverError) -> Self {
+        let diagnostic = match error {
+            ResolverError::ReadInOwnInitializer(token) => Diagnostic::at_token("Can't read local variable in its own initializer.", token)
+                .with_help("give the new variable a different name"),
+            ResolverError::AlreadyDeclared(token) => Diagnostic::at_token("Already a variable with this name in this scope.", token)
+                .with_help("assign to the existing variable instead of declaring it again"),
+            ResolverError::TopLevelReturn(token) => Diagnostic::at_token("Can't return from top-level code.", token),
+            ResolverError::ReturnFromInitializer(token) => Diagnostic::at_token("Can't return a value from an initializer.", token)
+                .with_note("'init' always returns the new instance"),
+            ResolverError::ThisOutsideClass(token) => Diagnostic::at_token("Can't use 'this' outside of a class.", token),
+            ResolverError::SuperOutsideClass(token) => Diagnostic::at_token("Can't use 'super' outside of a class.", token),
+            ResolverError::SuperWithoutSuperclass(token) => Diagnostic::at_token("Can't use 'super' in a class with no superclass.", token),
+            ResolverError::InheritFromSelf(token) => Diagnostic::at_token("A class can't inherit from itself.", token),
+        };
+        diagnostic.with_code(Some(error.code()))
+    }
+}
+
+impl From<&CompileError> for Diagnostic {
+    fn from(error: &CompileError) -> Self {
+        let diagnostic = match error {
+            CompileError::LimitExceeded(_, span, message) => Diagnostic::error(message)
+                .with_label(Label::primary(*span, "")),
+        };
+        diagnostic.with_code(Some(error.code()))
+    }
+}
+
+impl From<&LoxError> for Diagnostic {
+    fn from(error: &LoxError) -> Self {
+        let diagnostic = match error {
+            LoxError::OperandMustBeNumber(token) => Diagnostic::at_token("Operand must be a number.", token),
+            LoxError::OperandsMustBeNumbers(token) => Diagnostic::at_token("Operands must be numbers.", token),
+            LoxError::OperandsMustBeNumbersOrStrings(token) => Diagnostic::at_token("Operands must be two numbers or two strings.", token),
+            LoxError::InvalidOperator(token) => Diagnostic::at_token("Invalid operator.", token),
+            LoxError::UndefinedVariable(token) => Diagnostic::at_token(&format!("Undefined variable '{}'.", token.lexeme), token)
+                .with_help(&format!("declare it first with 'var {} = ...;'", token.lexeme)),
+            LoxError::NotCallable(token) => Diagnostic::at_token("Can only call functions and classes.", token),
+            LoxError::ArityMismatch(token, expected, got) => Diagnostic::at_token(&format!("Expected {expected} arguments but got {got}."), token),
+            LoxError::OnlyInstancesHaveProperties(token) => Diagnostic::at_token("Only instances have properties.", token),
+            LoxError::OnlyInstancesHaveFields(token) => Diagnostic::at_token("Only instances have fields.", token),
+            LoxError::UndefinedProperty(token) => Diagnostic::at_token(&format!("Undefined property '{}'.", token.lexeme), token),
+            LoxError::SuperclassMustBeClass(token) => Diagnostic::at_token("Superclass must be a class.", token),
+            LoxError::StackOverflow(token) => Diagnostic::at_token("Stack overflow.", token)
+                .with_note("Lox allows at most 1024 nested calls, counting the top-level script"),
+            LoxError::Io(_) | LoxError::Return(_) => Diagnostic::error(&error.to_string()),
+        };
+        diagnostic.with_code(error.code())
+    }
+}
+
+impl From<&StaticError> for Diagnostic {
+    fn from(error: &StaticError) -> Self {
+        match error {
+            StaticError::Token(e) => e.into(),
+            StaticError::Parser(e) => e.into(),
+            StaticError::Resolver(e) => e.into(),
+            StaticError::Compile(e) => e.into(),
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Prints diagnostics rustc-style: the message, the location, and the offending
+/// source lines with carets under the labelled spans.
+pub struct Renderer<'a> {
+    source_map: &'a SourceMap,
+    file: Option<&'a str>,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(source_map: &'a SourceMap) -> Self {
+        Self {
+            source_map,
+            file: None,
+            color: false,
+        }
+    }
+
+    pub fn with_file(mut self, file: &'a str) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    /// Whether to emit ANSI colour codes; enable only when writing to a terminal.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let severity_color = match diagnostic.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+        let mut out = String::new();
+        let heading = match diagnostic.code {
+            Some(code) => format!("{}[{code}]", diagnostic.severity.name()),
+            None => diagnostic.severity.name().to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "{}{}",
+            self.paint(severity_color, &heading),
+            self.paint(BOLD, &format!(": {}", diagnostic.message)),
+        );
+
+        let mut labels: Vec<(usize, usize, usize, &Label)> = diagnostic
+            .labels
+            .iter()
+            .map(|label| {
+                let start = clamp(self.source_map.source(), label.span.start);
+                let location = self.source_map.location(start);
+                let line_end = self.source_map.line_span(location.line).end;
+                let end = label.span.end.min(line_end).max(start);
+                let width = self.source_map.text(Span::new(start, end)).chars().count();
+                (location.line, location.column, width.max(1), label)
+            })
+            .collect();
+        labels.sort_by_key(|&(line, column, _, label)| (line, !label.primary, column));
+
+        let gutter = labels.iter().map(|&(line, ..)| line.to_string().len()).max().unwrap_or(1);
+        let blank = " ".repeat(gutter);
+        if let Some(&(line, column, ..)) = labels.iter().find(|(.., label)| label.primary) {
+            let file = self.file.unwrap_or("<input>");
+            let _ = writeln!(out, "{blank}{} {file}:{line}:{column}", self.paint(BLUE, "-->"));
+        }
+        if !labels.is_empty() {
+            let _ = writeln!(out, "{blank} {}", self.paint(BLUE, "|"));
+        }
+
+        let mut previous_line = None;
+        for &(line, column, width, label) in &labels {
+            let text = self.source_map.line_text(line);
+            if previous_line != Some(line) {
+                if previous_line.is_some_and(|previous| line > previous + 1) {
+                    let _ = writeln!(out, "{}", self.paint(BLUE, "..."));
+                }
+                let _ = writeln!(out, "{} {text}", self.paint(BLUE, &format!("{line:>gutter$} |")));
+                previous_line = Some(line);
+            }
+            // Keep tabs so the markers line up with the source line above them.
+            let padding: String = text.chars().take(column - 1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            let (marker, color) = if label.primary { ('^', severity_color) } else { ('-', BLUE) };
+            let underline = format!("{} {}", marker.to_string().repeat(width), label.text(self.source_map.source()));
+            let _ = writeln!(out, "{blank} {} {padding}{}", self.paint(BLUE, "|"), self.paint(color, underline.trim_end()));
+        }
+
+        for note in &diagnostic.notes {
+            let _ = writeln!(out, "{blank} {} note: {note}", self.paint(BLUE, "="));
+        }
+        if let Some(help) = &diagnostic.help {
+            let _ = writeln!(out, "{blank} {} help: {help}", self.paint(BLUE, "="));
+        }
+        out
+    }
+
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// A zero-width span at the very end of a file that ends in a newline points just
+/// past the last character instead of at an empty trailing line.
+fn clamp(source: &[u8], offset: usize) -> usize {
+    let offset = offset.min(source.len());
+    if offset != source.len() {
+        offset
+    } else if source.ends_with(b"\r\n") {
+        offset - 2
+    } else if source.ends_with(b"\n") {
+        offset - 1
+    } else {
+        offset
+    }
+}
+
+/// The JSON form of a [`Diagnostic`], one object per line on stderr with
+/// `--error-format=json`. Fields are only ever added to this schema, never renamed
+/// or removed.
+#[derive(Debug, Serialize)]
+pub struct JsonDiagnostic {
+    /// Stable error code such as `L0102`; see `rinterpreter --explain`.
+    pub code: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+    pub file: Option<String>,
+    /// Where the problem is; `null` for errors not tied to the source.
+    pub span: Option<JsonSpan>,
+    /// Other locations that explain the problem, such as an unclosed delimiter.
+    pub related: Vec<JsonSpan>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+/// A labelled span with both byte offsets and one-based line/column positions.
+#[derive(Debug, Serialize)]
+pub struct JsonSpan {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub label: Option<String>,
+}
+
+impl JsonSpan {
+    fn new(label: &Label, source_map: &SourceMap) -> Self {
+        let start = source_map.location(clamp(source_map.source(), label.span.start));
+        let end = if label.span.is_empty() { start } else { source_map.location(label.span.end) };
+        let text = label.text(source_map.source());
+        Self {
+            start: label.span.start,
+            end: label.span.end,
+            line: start.line,
+            column: start.column,
+            end_line: end.line,
+            end_column: end.column,
+            label: if text.is_empty() { None } else { Some(text) },
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn to_json(&self, source_map: &SourceMap, file: Option<&str>) -> JsonDiagnostic {
+        let (primary, related): (Vec<&Label>, Vec<&Label>) = self.labels.iter().partition(|label| label.primary);
+        JsonDiagnostic {
+            code: self.code.map(|code| code.to_string()),
+            severity: self.severity,
+            message: self.message.clone(),
+            file: file.map(str::to_string),
+            span: primary.first().map(|label| JsonSpan::new(label, source_map)),
+            related: related.into_iter().chain(primary.into_iter().skip(1)).map(|label| JsonSpan::new(label, source_map)).collect(),
+            notes: self.notes.clone(),
+            help: self.help.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn render_parse_errors(source: &[u8]) -> String {
+        let (tokens, _) = Scanner::new(source.to_vec()).scan_tokens();
+        let (_, errors) = Parser::new(tokens).parse();
+        let map = SourceMap::new(source);
+        let renderer = Renderer::new(&map).with_file("test.lox");
+        errors.iter().map(|e| renderer.render(&e.into())).collect()
+    }
+
+    #[test]
+    fn test_render_unclosed_paren() {
+        assert_eq!(
+            render_parse_errors(b"print (1 + 2;\n"),
+            "\
+error[L0102]: Expect ')' after expression.
+ --> test.lox:1:13
+  |
+1 | print (1 + 2;
+  |             ^
+  |       - opening parenthesis here
+"
+        );
+    }
+
+    #[test]
+    fn test_render_across_lines() {
+        assert_eq!(
+            render_parse_errors(b"{\n  print 1;\n"),
+            "\
+error[L0103]: Expect '}' after block.
+ --> test.lox:2:11
+  |
+1 | {
+  | - opening brace here
+2 |   print 1;
+  |           ^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_crlf_end_of_file() {
+        assert_eq!(
+            render_parse_errors(b"print 1\r\n"),
+            "\
+error[L0104]: Expect ';' after value.
+ --> test.lox:1:8
+  |
+1 | print 1
+  |        ^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_invalid_utf8() {
+        let map = SourceMap::new(b"print 1;\xff\n");
+        let diagnostic = Diagnostic::error("Unexpected character.").with_label(Label::primary(Span::new(8, 9), ""));
+        assert_eq!(
+            Renderer::new(&map).render(&diagnostic),
+            "\
+error: Unexpected character.
+ --> <input>:1:9
+  |
+1 | print 1;\u{fffd}
+  |         ^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_after_invalid_utf8() {
+        assert_eq!(
+            render_parse_errors(b"// \xff\xfe\nprint 1 + 2"),
+            "\
+error[L0104]: Expect ';' after value.
+ --> test.lox:2:12
+  |
+2 | print 1 + 2
+  |            ^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_notes_and_help() {
+        let map = SourceMap::new(b"var s = \"abc");
+        let diagnostic: Diagnostic = (&TokenError::UnterminatedString(1, Span::new(8, 12))).into();
+        assert_eq!(
+            Renderer::new(&map).render(&diagnostic),
+            "\
+error[L0001]: Unterminated string.
+ --> <input>:1:9
+  |
+1 | var s = \"abc
+  |         ^ string starts here
+  = help: add a closing '\"' to end the string
+"
+        );
+    }
+
+    #[test]
+    fn test_render_color() {
+        let map = SourceMap::new(b"x;");
+        let diagnostic = Diagnostic::error("Oops.").with_label(Label::primary(Span::new(0, 1), ""));
+        let rendered = Renderer::new(&map).with_color(true).render(&diagnostic);
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m"));
+        assert!(!Renderer::new(&map).render(&diagnostic).contains('\x1b'));
+    }
+
+    #[test]
+    fn test_runtime_errors_have_distinct_codes() {
+        let code = |source: &str| match crate::run(source.as_bytes()) {
+            crate::RunResult::RuntimeError(error) => Diagnostic::from(&error).code.map(|code| code.to_string()),
+            _ => None,
+        };
+        assert_eq!(code("-\"a\";").as_deref(), Some("L0301"));
+        assert_eq!(code("\"a\" * 2;").as_deref(), Some("L0310"));
+        assert_eq!(code("1 .x;").as_deref(), Some("L0306"));
+        assert_eq!(code("1 .x = 2;").as_deref(), Some("L0311"));
+    }
+
+    #[test]
+    fn test_json() {
+        let source = "print (1 + 2;\n";
+        let (tokens, _) = Scanner::new(source.as_bytes().to_vec()).scan_tokens();
+        let (_, errors) = Parser::new(tokens).parse();
+        let json = Diagnostic::from(&errors[0]).to_json(&SourceMap::new(source.as_bytes()), Some("test.lox"));
+        assert_eq!(
+            serde_json::to_value(&json).unwrap(),
+            serde_json::json!({
+                "code": "L0102",
+                "severity": "error",
+                "message": "Expect ')' after expression.",
+                "file": "test.lox",
+                "span": { "start": 12, "end": 13, "line": 1, "column": 13, "end_line": 1, "end_column": 14, "label": null },
+                "related": [
+                    { "start": 6, "end": 7, "line": 1, "column": 7, "end_line": 1, "end_column": 8, "label": "opening parenthesis here" },
+                ],
+                "notes": [],
+                "help": null,
+            })
+        );
+    }
+}
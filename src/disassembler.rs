@@ -0,0 +1,164 @@
+use super::chunk::{Chunk, Constant, Function, OpCode};
+use super::span::SourceMap;
+
+/// Prints bytecode in the style of clox's `debug.c`: one instruction per line with
+/// its offset, source line (`|` when unchanged), opcode, operands and the value of
+/// any constant it refers to.
+///
+/// ```text
+/// == <script> ==
+/// 0000    1 OP_CONSTANT         0 '1'
+/// 0003    | OP_PRINT
+/// ```
+#[derive(Default)]
+pub struct Disassembler<'a> {
+    source_map: Option<&'a SourceMap>,
+}
+
+impl<'a> Disassembler<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also print each source line as a comment before the instructions compiled from it.
+    pub fn with_source(mut self, source_map: &'a SourceMap) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    /// Disassembles `function` followed by every function nested in it.
+    pub fn function(&self, function: &Function) -> String {
+        let mut out = self.chunk(&function.chunk, &function.to_string());
+        for constant in &function.chunk.constants {
+            if let Constant::Function(nested) = constant {
+                out.push('\n');
+                out.push_str(&self.function(nested));
+            }
+        }
+        out
+    }
+
+    pub fn chunk(&self, chunk: &Chunk, name: &str) -> String {
+        let mut out = format!("== {name} ==\n");
+        let mut offset = 0;
+        let mut source_line = None;
+        while offset < chunk.code.len() {
+            let line = chunk.line(offset);
+            if let Some(source_map) = self.source_map.filter(|_| source_line != Some(line)) {
+                if (1..=source_map.line_count()).contains(&(line as usize)) {
+                    out.push_str(&format!("          // {}\n", source_map.line_text(line as usize).trim()));
+                }
+                source_line = Some(line);
+            }
+            let (text, next) = self.instruction(chunk, offset);
+            out.push_str(&text);
+            out.push('\n');
+            offset = next;
+        }
+        out
+    }
+
+    /// Formats the instruction at `offset`, returning it with the offset of the next one.
+    pub fn instruction(&self, chunk: &Chunk, offset: usize) -> (String, usize) {
+        let line = chunk.line(offset);
+        let line = if offset > 0 && chunk.line(offset - 1) == line { "   |".to_string() } else { format!("{line:4}") };
+        let prefix = format!("{offset:04} {line} ");
+        let Some(op) = OpCode::from_byte(chunk.code[offset]) else {
+            return (format!("{prefix}unknown opcode {}", chunk.code[offset]), offset + 1);
+        };
+        let name = op_name(op);
+        match op {
+            OpCode::Constant
+            | OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetProperty
+            | OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Class
+            | OpCode::Method => {
+                let index = chunk.read_u16(offset + 1);
+                let text = format!("{prefix}{name:<16} {index:4} '{}'", chunk.constants[index as usize]);
+                (text, offset + 3)
+            },
+            OpCode::GetLocal | OpCode::SetLocal | OpCode::GetUpvalue | OpCode::SetUpvalue | OpCode::Call => {
+                (format!("{prefix}{name:<16} {:4}", chunk.code[offset + 1]), offset + 2)
+            },
+            OpCode::Jump | OpCode::JumpIfFalse | OpCode::Loop => {
+                let distance = chunk.read_u16(offset + 1) as usize;
+                let target = if op == OpCode::Loop { (offset + 3).wrapping_sub(distance) } else { offset + 3 + distance };
+                (format!("{prefix}{name:<16} {offset:4} -> {target}"), offset + 3)
+            },
+            OpCode::Closure => {
+                let index = chunk.read_u16(offset + 1);
+                let constant = &chunk.constants[index as usize];
+                let mut text = format!("{prefix}{name:<16} {index:4} '{constant}'");
+                // The VM reads the captured variables from the function, not from operands.
+                if let Constant::Function(function) = constant {
+                    for upvalue in &function.upvalues {
+                        let kind = if upvalue.is_local { "local" } else { "upvalue" };
+                        text.push_str(&format!("\n{offset:04}    |                     {kind} {}", upvalue.index));
+                    }
+                }
+                (text, offset + 3)
+            },
+            _ => (format!("{prefix}{name}"), offset + 1),
+        }
+    }
+}
+
+/// The clox name of `op`, such as `OP_GET_LOCAL` for [`OpCode::GetLocal`].
+fn op_name(op: OpCode) -> String {
+    let mut name = "OP".to_string();
+    for c in format!("{op:?}").chars() {
+        if c.is_ascii_uppercase() {
+            name.push('_');
+        }
+        name.push(c.to_ascii_uppercase());
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile;
+
+    #[test]
+    fn test_disassemble_script() {
+        let source = "var a = \"one\";\nwhile (a) a = nil;";
+        let script = compile(source.as_bytes()).unwrap();
+        assert_eq!(Disassembler::new().function(&script), "\
+== <script> ==
+0000    1 OP_CONSTANT         0 'one'
+0003    | OP_DEFINE_GLOBAL    1 'a'
+0006    2 OP_GET_GLOBAL       1 'a'
+0009    | OP_JUMP_IF_FALSE    9 -> 21
+0012    | OP_POP
+0013    | OP_NIL
+0014    | OP_SET_GLOBAL       1 'a'
+0017    | OP_POP
+0018    | OP_LOOP            18 -> 6
+0021    | OP_POP
+0022    | OP_NIL
+0023    | OP_RETURN
+");
+    }
+
+    #[test]
+    fn test_disassemble_nested_functions_with_source() {
+        let source = "fun outer(x) {\n  fun inner() { return x; }\n}";
+        let script = compile(source.as_bytes()).unwrap();
+        let source_map = SourceMap::new(source.as_bytes());
+        let text = Disassembler::new().with_source(&source_map).function(&script);
+        assert!(text.contains("\
+== <fn outer> ==
+          // fun inner() { return x; }
+0000    2 OP_CLOSURE          0 '<fn inner>'
+0000    |                     local 1
+          // }
+0003    3 OP_NIL
+"));
+        assert!(text.contains("== <fn inner> ==\n          // fun inner() { return x; }\n0000    2 OP_GET_UPVALUE      0\n"));
+    }
+}
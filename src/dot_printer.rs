@@ -0,0 +1,253 @@
+use std::cell::{Cell, RefCell};
+
+use super::ast::*;
+use super::errors::LoxError;
+
+/// Exports the AST as a Graphviz DOT digraph, one box per node with edges to its
+/// children in source order. Render it with `dot -Tsvg`.
+#[derive(Default)]
+pub struct DotPrinter {
+    out: RefCell<String>,
+    next_id: Cell<usize>,
+}
+
+impl DotPrinter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn print(&self, expr: &Expr) -> Result<String, LoxError> {
+        self.graph(|| expr.accept(self).map(|_| ()))
+    }
+
+    /// Graphs a whole program under a single `Program` root.
+    pub fn print_program(&self, statements: &[Stmt]) -> Result<String, LoxError> {
+        self.graph(|| {
+            let root = self.node("Program");
+            for statement in statements {
+                let child = statement.accept(self)?;
+                self.edge(root, child, None);
+            }
+            Ok(())
+        })
+    }
+
+    fn graph(&self, body: impl FnOnce() -> Result<(), LoxError>) -> Result<String, LoxError> {
+        self.out.replace("digraph ast {\n  ordering=out;\n  node [shape=box, fontname=\"monospace\"];\n".to_string());
+        self.next_id.set(0);
+        body()?;
+        let mut out = self.out.take();
+        out.push_str("}\n");
+        Ok(out)
+    }
+
+    fn node(&self, label: &str) -> usize {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.out.borrow_mut().push_str(&format!("  n{id} [label=\"{}\"];\n", escape(label)));
+        id
+    }
+
+    fn edge(&self, from: usize, to: usize, label: Option<&str>) {
+        let mut out = self.out.borrow_mut();
+        match label {
+            Some(label) => out.push_str(&format!("  n{from} -> n{to} [label=\"{}\"];\n", escape(label))),
+            None => out.push_str(&format!("  n{from} -> n{to};\n")),
+        }
+    }
+
+    /// A node labelled `label` with an unlabelled edge to each of `children`.
+    fn tree(&self, label: &str, children: Vec<&Expr>) -> Result<usize, LoxError> {
+        let id = self.node(label);
+        for child in children {
+            let child_id = child.accept(self)?;
+            self.edge(id, child_id, None);
+        }
+        Ok(id)
+    }
+
+    fn child_expr(&self, parent: usize, expr: &Expr, label: &str) -> Result<(), LoxError> {
+        let child = expr.accept(self)?;
+        self.edge(parent, child, Some(label));
+        Ok(())
+    }
+
+    fn child_stmt(&self, parent: usize, stmt: &Stmt, label: Option<&str>) -> Result<(), LoxError> {
+        let child = stmt.accept(self)?;
+        self.edge(parent, child, label);
+        Ok(())
+    }
+
+    fn function(&self, kind: &str, stmt: &FunctionStmt) -> Result<usize, LoxError> {
+        let params: Vec<&str> = stmt.params.iter().map(|param| param.lexeme.as_str()).collect();
+        let id = self.node(&format!("{kind} {}({})", stmt.name.lexeme, params.join(", ")));
+        for statement in stmt.body.iter() {
+            self.child_stmt(id, statement, None)?;
+        }
+        Ok(id)
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl Visitor<usize> for DotPrinter {
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<usize, LoxError> {
+        self.tree(&format!("Binary {}", expr.operator.lexeme), vec![&expr.left, &expr.right])
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<usize, LoxError> {
+        self.tree("Grouping", vec![&expr.expression])
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<usize, LoxError> {
+        Ok(self.node(&expr.source_text()))
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<usize, LoxError> {
+        self.tree(&format!("Unary {}", expr.operator.lexeme), vec![&expr.right])
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<usize, LoxError> {
+        Ok(self.node(&format!("Variable {}", expr.name.lexeme)))
+    }
+
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<usize, LoxError> {
+        self.tree(&format!("Assign {}", expr.name.lexeme), vec![&expr.value])
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<usize, LoxError> {
+        self.tree(&format!("Logical {}", expr.operator.lexeme), vec![&expr.left, &expr.right])
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<usize, LoxError> {
+        let id = self.node("Call");
+        self.child_expr(id, &expr.callee, "callee")?;
+        for (i, argument) in expr.arguments.iter().enumerate() {
+            self.child_expr(id, argument, &format!("arg {i}"))?;
+        }
+        Ok(id)
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<usize, LoxError> {
+        self.tree(&format!("Get .{}", expr.name.lexeme), vec![&expr.object])
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<usize, LoxError> {
+        let id = self.node(&format!("Set .{}", expr.name.lexeme));
+        self.child_expr(id, &expr.object, "object")?;
+        self.child_expr(id, &expr.value, "value")?;
+        Ok(id)
+    }
+
+    fn visit_this_expr(&self, _expr: &ThisExpr) -> Result<usize, LoxError> {
+        Ok(self.node("This"))
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<usize, LoxError> {
+        Ok(self.node(&format!("Super .{}", expr.method.lexeme)))
+    }
+}
+
+impl StmtVisitor<usize> for DotPrinter {
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<usize, LoxError> {
+        self.tree("Expression", vec![&stmt.expression])
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<usize, LoxError> {
+        self.tree("Print", vec![&stmt.expression])
+    }
+
+    fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<usize, LoxError> {
+        self.tree(&format!("Var {}", stmt.name.lexeme), stmt.initializer.iter().collect())
+    }
+
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<usize, LoxError> {
+        let id = self.node("Block");
+        for statement in &stmt.statements {
+            self.child_stmt(id, statement, None)?;
+        }
+        Ok(id)
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<usize, LoxError> {
+        let id = self.node("If");
+        self.child_expr(id, &stmt.condition, "condition")?;
+        self.child_stmt(id, &stmt.then_branch, Some("then"))?;
+        if let Some(else_branch) = &stmt.else_branch {
+            self.child_stmt(id, else_branch, Some("else"))?;
+        }
+        Ok(id)
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<usize, LoxError> {
+        let id = self.node("While");
+        self.child_expr(id, &stmt.condition, "condition")?;
+        self.child_stmt(id, &stmt.body, Some("body"))?;
+        Ok(id)
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<usize, LoxError> {
+        self.function("Function", stmt)
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<usize, LoxError> {
+        self.tree("Return", stmt.value.iter().collect())
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<usize, LoxError> {
+        let mut label = format!("Class {}", stmt.name.lexeme);
+        if let Some(superclass) = &stmt.superclass {
+            label.push_str(&format!(" < {}", superclass.name.lexeme));
+        }
+        let id = self.node(&label);
+        for method in &stmt.methods {
+            let child = self.function("Method", method)?;
+            self.edge(id, child, None);
+        }
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_expression_graph() {
+        let statements = parse(b"print (1 + 2) * \"a\\\";").unwrap();
+        let Stmt::Print(print) = &statements[0] else { panic!("expected a print statement") };
+        assert_eq!(DotPrinter::new().print(&print.expression).unwrap(), "\
+digraph ast {
+  ordering=out;
+  node [shape=box, fontname=\"monospace\"];
+  n0 [label=\"Binary *\"];
+  n1 [label=\"Grouping\"];
+  n2 [label=\"Binary +\"];
+  n3 [label=\"1\"];
+  n2 -> n3;
+  n4 [label=\"2\"];
+  n2 -> n4;
+  n1 -> n2;
+  n0 -> n1;
+  n5 [label=\"\\\"a\\\\\\\"\"];
+  n0 -> n5;
+}
+");
+    }
+
+    #[test]
+    fn test_program_graph() {
+        let statements = parse(b"if (x) f(1); else { return; }").unwrap();
+        let dot = DotPrinter::new().print_program(&statements).unwrap();
+        assert!(dot.contains("n0 [label=\"Program\"];"));
+        assert!(dot.contains("n1 -> n2 [label=\"condition\"];"));
+        assert!(dot.contains("n4 -> n5 [label=\"callee\"];"));
+        assert!(dot.contains("n4 -> n6 [label=\"arg 0\"];"));
+        assert!(dot.contains("n1 -> n3 [label=\"then\"];"));
+        assert!(dot.contains("n1 -> n7 [label=\"else\"];"));
+        assert!(dot.contains("n0 -> n1;"));
+    }
+}
@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::errors::LoxError;
+use super::symbol::Symbol;
+use super::token::Token;
+use super::value::Value;
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<Symbol, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(&mut self, name: Symbol, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, LoxError> {
+        if let Some(value) = self.values.get(&name.lexeme.symbol()) {
+            return Ok(value.clone());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(LoxError::UndefinedVariable(name.clone())),
+        }
+    }
+
+    /// Reads `name` from the environment exactly `distance` hops up the chain, as
+    /// computed by the resolver.
+    pub fn get_at(&self, distance: usize, name: Symbol) -> Option<Value> {
+        if distance == 0 {
+            return self.values.get(&name).cloned();
+        }
+        self.enclosing.as_ref()?.borrow().get_at(distance - 1, name)
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), LoxError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme.symbol()) {
+            *slot = value;
+            return Ok(());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(LoxError::UndefinedVariable(name.clone())),
+        }
+    }
+
+    pub fn assign_at(&mut self, distance: usize, name: &Token, value: Value) -> Result<(), LoxError> {
+        if distance == 0 {
+            self.values.insert(name.lexeme.symbol(), value);
+            return Ok(());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+            None => Err(LoxError::UndefinedVariable(name.clone())),
+        }
+    }
+}
@@ -1,5 +1,7 @@
-use crate::token_type::TokenType;
+use crate::codes::ErrorCode;
+use crate::span::Span;
 use crate::token::Token;
+use crate::value::Value;
 
 #[derive(Debug, thiserror::Error)]
 pub enum FileError {
@@ -7,27 +9,201 @@ pub enum FileError {
     ReadError(#[from] std::io::Error),
 }
 
+/// Why a `.loxc` file could not be used.
+#[derive(Debug, thiserror::Error)]
+pub enum LoxcError {
+    #[error("Not a compiled Lox file.")]
+    BadMagic,
+    #[error("Compiled with format version {0}, but this build reads version {1}.")]
+    UnsupportedVersion(u16, u16),
+    #[error("Compiled from different source.")]
+    Stale,
+    #[error("Compiled file is damaged: its checksum does not match.")]
+    ChecksumMismatch,
+    #[error("Corrupt compiled file: {0}")]
+    Corrupt(#[from] bincode::Error),
+    #[error("Failed to access compiled file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum TokenError {
     #[error("[line {0}] Error: Unexpected character \"{1}\".")]
-    InvalidToken(u64, char),
+    InvalidToken(u64, char, Span),
     #[error("[line {0}] Error: Unterminated string.")]
-    UnterminatedString(u64),
+    UnterminatedString(u64, Span),
     #[error("[line {0}] Error: Unterminated block comment.")]
-    UnterminatedBlockComment(u64),
+    UnterminatedBlockComment(u64, Span),
+    #[error("[line {0}] Error: Invalid UTF-8 in string.")]
+    InvalidUtf8(u64, Span),
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum LoxError {
-
+    #[error("[line {}] Error at '{}': Operand must be a number.", .0.line, .0.lexeme)]
+    OperandMustBeNumber(Token),
+    #[error("[line {}] Error at '{}': Operands must be numbers.", .0.line, .0.lexeme)]
+    OperandsMustBeNumbers(Token),
+    #[error("[line {}] Error at '{}': Operands must be two numbers or two strings.", .0.line, .0.lexeme)]
+    OperandsMustBeNumbersOrStrings(Token),
+    #[error("[line {}] Error at '{}': Invalid operator.", .0.line, .0.lexeme)]
+    InvalidOperator(Token),
+    #[error("[line {}] Error: Undefined variable '{}'.", .0.line, .0.lexeme)]
+    UndefinedVariable(Token),
+    #[error("[line {}] Error at '{}': Can only call functions and classes.", .0.line, .0.lexeme)]
+    NotCallable(Token),
+    #[error("[line {}] Error at '{}': Expected {} arguments but got {}.", .0.line, .0.lexeme, .1, .2)]
+    ArityMismatch(Token, usize, usize),
+    #[error("[line {}] Error at '{}': Only instances have properties.", .0.line, .0.lexeme)]
+    OnlyInstancesHaveProperties(Token),
+    #[error("[line {}] Error at '{}': Only instances have fields.", .0.line, .0.lexeme)]
+    OnlyInstancesHaveFields(Token),
+    #[error("[line {}] Error: Undefined property '{}'.", .0.line, .0.lexeme)]
+    UndefinedProperty(Token),
+    #[error("[line {}] Error at '{}': Superclass must be a class.", .0.line, .0.lexeme)]
+    SuperclassMustBeClass(Token),
+    /// Raised when calls nest deeper than [`FRAMES_MAX`](crate::vm::FRAMES_MAX) frames.
+    #[error("[line {}] Error at '{}': Stack overflow.", .0.line, .0.lexeme)]
+    StackOverflow(Token),
+    #[error("Failed to write output: {0}")]
+    Io(std::io::Error),
+    /// Not an error: unwinds the interpreter out of a function body when `return` executes.
+    #[error("Can't return from top-level code.")]
+    Return(Value),
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum ParserError {
-    #[error("Expect '{0:#?}' after expression")]
-    ExpectedToken(TokenType),
-    #[error("[line {0}] Error at '{1}': Expect ')' after expression.")]
-    At(u64, String),
-    #[error("[line {0}] Error at end: Expect ')' after expression.")]
-    Eof(u64)
+    #[error("[line {line}] Error at '{lexeme}': {message}")]
+    At {
+        line: u64,
+        /// The offending token.
+        lexeme: String,
+        message: String,
+        span: Span,
+        /// The unclosed opening delimiter, when a closing one was expected.
+        related: Option<Span>,
+        code: ErrorCode,
+    },
+    /// Like [`ParserError::At`], for input that ended too early.
+    #[error("[line {line}] Error at end: {message}")]
+    Eof {
+        line: u64,
+        message: String,
+        span: Span,
+        related: Option<Span>,
+        code: ErrorCode,
+    },
+    #[error("[line {0}] Error at '{1}': Invalid assignment target.")]
+    InvalidAssignmentTarget(u64, String, Span),
+}
+
+/// A program the bytecode compiler cannot encode within the limits of its
+/// instruction format.
+#[derive(Debug, thiserror::Error)]
+pub enum CompileError {
+    /// Line and span of the construct that does not fit, and the limit it hit.
+    #[error("[line {0}] Error: {2}")]
+    LimitExceeded(u64, Span, &'static str),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResolverError {
+    #[error("[line {}] Error at '{}': Can't read local variable in its own initializer.", .0.line, .0.lexeme)]
+    ReadInOwnInitializer(Token),
+    #[error("[line {}] Error at '{}': Already a variable with this name in this scope.", .0.line, .0.lexeme)]
+    AlreadyDeclared(Token),
+    #[error("[line {}] Error at '{}': Can't return from top-level code.", .0.line, .0.lexeme)]
+    TopLevelReturn(Token),
+    #[error("[line {}] Error at '{}': Can't return a value from an initializer.", .0.line, .0.lexeme)]
+    ReturnFromInitializer(Token),
+    #[error("[line {}] Error at '{}': Can't use 'this' outside of a class.", .0.line, .0.lexeme)]
+    ThisOutsideClass(Token),
+    #[error("[line {}] Error at '{}': Can't use 'super' outside of a class.", .0.line, .0.lexeme)]
+    SuperOutsideClass(Token),
+    #[error("[line {}] Error at '{}': Can't use 'super' in a class with no superclass.", .0.line, .0.lexeme)]
+    SuperWithoutSuperclass(Token),
+    #[error("[line {}] Error at '{}': A class can't inherit from itself.", .0.line, .0.lexeme)]
+    InheritFromSelf(Token),
+}
+
+impl TokenError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            TokenError::InvalidToken(..) => ErrorCode::UnexpectedCharacter,
+            TokenError::UnterminatedString(..) => ErrorCode::UnterminatedString,
+            TokenError::UnterminatedBlockComment(..) => ErrorCode::UnterminatedBlockComment,
+            TokenError::InvalidUtf8(..) => ErrorCode::InvalidUtf8,
+        }
+    }
+}
+
+impl LoxError {
+    /// `None` for failures that aren't a problem with the program itself.
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            LoxError::OperandMustBeNumber(_) => Some(ErrorCode::OperandMustBeNumber),
+            LoxError::OperandsMustBeNumbers(_) => Some(ErrorCode::OperandsMustBeNumbers),
+            LoxError::OperandsMustBeNumbersOrStrings(_) => Some(ErrorCode::OperandsMustBeNumbersOrStrings),
+            LoxError::UndefinedVariable(_) => Some(ErrorCode::UndefinedVariable),
+            LoxError::NotCallable(_) => Some(ErrorCode::NotCallable),
+            LoxError::ArityMismatch(..) => Some(ErrorCode::ArityMismatch),
+            LoxError::OnlyInstancesHaveProperties(_) => Some(ErrorCode::OnlyInstancesHaveProperties),
+            LoxError::OnlyInstancesHaveFields(_) => Some(ErrorCode::OnlyInstancesHaveFields),
+            LoxError::UndefinedProperty(_) => Some(ErrorCode::UndefinedProperty),
+            LoxError::SuperclassMustBeClass(_) => Some(ErrorCode::SuperclassMustBeClass),
+            LoxError::StackOverflow(_) => Some(ErrorCode::StackOverflow),
+            LoxError::InvalidOperator(_) | LoxError::Io(_) | LoxError::Return(_) => None,
+        }
+    }
+}
+
+impl ParserError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ParserError::At { code, .. } | ParserError::Eof { code, .. } => *code,
+            ParserError::InvalidAssignmentTarget(..) => ErrorCode::InvalidAssignmentTarget,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            ParserError::At { span, .. } | ParserError::Eof { span, .. } | ParserError::InvalidAssignmentTarget(.., span) => *span,
+        }
+    }
+}
+
+impl CompileError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            CompileError::LimitExceeded(..) => ErrorCode::CompilerLimit,
+        }
+    }
+}
+
+impl ResolverError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ResolverError::ReadInOwnInitializer(_) => ErrorCode::ReadInOwnInitializer,
+            ResolverError::AlreadyDeclared(_) => ErrorCode::AlreadyDeclared,
+            ResolverError::TopLevelReturn(_) => ErrorCode::TopLevelReturn,
+            ResolverError::ReturnFromInitializer(_) => ErrorCode::ReturnFromInitializer,
+            ResolverError::ThisOutsideClass(_) => ErrorCode::ThisOutsideClass,
+            ResolverError::SuperOutsideClass(_) => ErrorCode::SuperOutsideClass,
+            ResolverError::SuperWithoutSuperclass(_) => ErrorCode::SuperWithoutSuperclass,
+            ResolverError::InheritFromSelf(_) => ErrorCode::InheritFromSelf,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StaticError {
+    #[error(transparent)]
+    Token(#[from] TokenError),
+    #[error(transparent)]
+    Parser(#[from] ParserError),
+    #[error(transparent)]
+    Resolver(#[from] ResolverError),
+    #[error(transparent)]
+    Compile(#[from] CompileError),
 }
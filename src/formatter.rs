@@ -0,0 +1,705 @@
+use std::cell::Cell;
+
+use super::ast::*;
+use super::errors::{LoxError, StaticError};
+use super::parser::Parser;
+use super::scanner::Scanner;
+use super::span::{SourceMap, Span};
+use super::token::{Comment, Token};
+
+/// Layout settings for [`format_source`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Spaces per level of indentation.
+    pub indent: usize,
+    /// Lines longer than this are broken at operators and between arguments where possible.
+    pub line_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            line_width: 80,
+        }
+    }
+}
+
+/// Prints `source` back as canonical Lox, keeping its comments and single blank
+/// lines between statements. Source with syntax errors is left alone.
+pub fn format_source(source: &str, options: FormatOptions) -> Result<String, Vec<StaticError>> {
+    let mut scanner = Scanner::new(source.as_bytes().to_vec());
+    let (tokens, token_errors) = scanner.scan_tokens();
+    let (statements, parser_errors) = Parser::new(tokens).parse();
+    if !token_errors.is_empty() || !parser_errors.is_empty() {
+        return Err(crate::static_errors(token_errors, parser_errors));
+    }
+
+    let formatter = Formatter {
+        source_map: SourceMap::new(source.as_bytes()),
+        comments: scanner.comments(),
+        next_comment: Cell::new(0),
+    };
+    let mut parts = formatter
+        .sequence(&statements, Stmt::span, |stmt| stmt.accept(&formatter), source.len())
+        .expect("formatting can't fail");
+    if parts.is_empty() {
+        return Ok(String::new());
+    }
+    parts.push(Doc::HardLine);
+    Ok(render(&Doc::Concat(parts), options))
+}
+
+/// A document in the style of Wadler's "prettier printer": text plus line breaks that
+/// are only taken when the enclosing group doesn't fit on the current line.
+#[derive(Debug, Clone)]
+enum Doc {
+    Text(String),
+    /// A space, or a line break when the group is broken.
+    Line,
+    /// Nothing, or a line break when the group is broken.
+    SoftLine,
+    /// Always a line break.
+    HardLine,
+    Concat(Vec<Doc>),
+    /// Indents the line breaks inside by one level.
+    Nest(Box<Doc>),
+    /// Lays out its contents flat if they fit, otherwise breaks all of its own lines.
+    Group(Box<Doc>),
+}
+
+impl Doc {
+    fn text(text: &str) -> Doc {
+        Doc::Text(text.to_string())
+    }
+
+    fn nest(doc: Doc) -> Doc {
+        Doc::Nest(Box::new(doc))
+    }
+
+    fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+
+    /// `docs` separated by `,` and a breakable space, for argument and parameter lists.
+    fn comma_separated(docs: Vec<Doc>) -> Doc {
+        let mut parts = Vec::new();
+        for (i, doc) in docs.into_iter().enumerate() {
+            if i > 0 {
+                parts.push(Doc::text(","));
+                parts.push(Doc::Line);
+            }
+            parts.push(doc);
+        }
+        Doc::Concat(parts)
+    }
+
+    /// `(items)` that breaks one item per line, indented, when it doesn't fit.
+    fn parenthesized(docs: Vec<Doc>) -> Doc {
+        if docs.is_empty() {
+            return Doc::text("()");
+        }
+        Doc::group(Doc::Concat(vec![
+            Doc::text("("),
+            Doc::nest(Doc::Concat(vec![Doc::SoftLine, Doc::comma_separated(docs)])),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ]))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+fn render(doc: &Doc, options: FormatOptions) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    let mut stack = vec![(0, Mode::Break, doc)];
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                out.push_str(text);
+                column = match text.rfind('\n') {
+                    Some(newline) => text[newline + 1..].chars().count(),
+                    None => column + text.chars().count(),
+                };
+            },
+            Doc::Line if mode == Mode::Flat => {
+                out.push(' ');
+                column += 1;
+            },
+            Doc::SoftLine if mode == Mode::Flat => {},
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                out.truncate(out.trim_end_matches(' ').len());
+                out.push('\n');
+                out.push_str(&" ".repeat(indent));
+                column = indent;
+            },
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+            Doc::Nest(doc) => stack.push((indent + options.indent, mode, doc)),
+            Doc::Group(doc) => {
+                let width = options.line_width.saturating_sub(column);
+                let mode = if mode == Mode::Flat || fits(width, doc, &stack) { Mode::Flat } else { Mode::Break };
+                stack.push((indent, mode, doc));
+            },
+        }
+    }
+    out.truncate(out.trim_end_matches(' ').len());
+    out
+}
+
+/// Whether `doc` laid out flat, followed by whatever comes after it up to the next
+/// line break, fits in `width` columns.
+fn fits(width: usize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut remaining = width as isize;
+    let mut stack = vec![(Mode::Flat, doc)];
+    let mut rest = rest.iter().rev();
+    loop {
+        let (mode, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some(&(_, mode, doc)) => (mode, doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => {
+                if text.contains('\n') {
+                    return true;
+                }
+                remaining -= text.chars().count() as isize;
+            },
+            Doc::Line if mode == Mode::Flat => remaining -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {},
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (mode, doc))),
+            Doc::Nest(doc) | Doc::Group(doc) => stack.push((mode, doc)),
+        }
+        if remaining < 0 {
+            return false;
+        }
+    }
+}
+
+/// Builds the [`Doc`] for a program. Comments are handed out in source order, each
+/// to the nearest statement or token as the program is laid out.
+struct Formatter<'a> {
+    source_map: SourceMap,
+    comments: &'a [Comment],
+    next_comment: Cell<usize>,
+}
+
+impl Formatter<'_> {
+    /// An expression with the comments around it that belong to it.
+    fn doc(&self, expr: &Expr) -> Result<Doc, LoxError> {
+        let span = expr.span();
+        Ok(Doc::Concat(vec![self.leading(span.start), expr.accept(self)?, self.inline_after(span.end)]))
+    }
+
+    /// A name with the comments around it that belong to it.
+    fn name(&self, token: &Token) -> Doc {
+        Doc::Concat(vec![self.leading(token.span.start), Doc::text(&token.lexeme), self.inline_after(token.span.end)])
+    }
+
+    /// `/* */` comments on the same line between `end` and the next token, which
+    /// stay right after the code ending at `end`.
+    fn inline_after(&self, end: usize) -> Doc {
+        let next = self.token_after(end);
+        let mut parts = Vec::new();
+        while let Some(comment) = self.take_comment(|comment| {
+            comment.span.start < next && comment.text.starts_with("/*") && self.line(comment.span.start) == self.line(end)
+        }) {
+            parts.push(Doc::text(" "));
+            parts.push(Doc::text(&comment.text));
+        }
+        Doc::Concat(parts)
+    }
+
+    /// Lays out `items` one per line, placing each comment before the item that follows
+    /// it, or after the item it shares a line with, and keeping single blank lines.
+    /// `end` is the offset where the enclosing block closes.
+    fn sequence<T>(
+        &self,
+        items: &[T],
+        span: impl Fn(&T) -> Span,
+        doc: impl Fn(&T) -> Result<Doc, LoxError>,
+        end: usize,
+    ) -> Result<Vec<Doc>, LoxError> {
+        let mut parts = Vec::new();
+        let mut previous_end = None;
+        for item in items {
+            let span = span(item);
+            while let Some(comment) = self.take_comment(|comment| comment.span.start < span.start) {
+                self.separate(&mut parts, previous_end, comment.span.start);
+                parts.push(Doc::text(comment.text.trim_end()));
+                previous_end = Some(comment.span.end);
+            }
+            self.separate(&mut parts, previous_end, span.start);
+            parts.push(doc(item)?);
+            previous_end = Some(span.end);
+
+            // Comments left inside the item, or after it on the same line, trail it.
+            let last_line = self.line(span.end.saturating_sub(1));
+            while let Some(comment) = self.take_comment(|comment| {
+                comment.span.start < span.end || (comment.span.start < end && self.line(comment.span.start) == last_line)
+            }) {
+                parts.push(Doc::text(" "));
+                parts.push(Doc::text(comment.text.trim_end()));
+                previous_end = Some(span.end.max(comment.span.end));
+            }
+        }
+        while let Some(comment) = self.take_comment(|comment| comment.span.start < end) {
+            self.separate(&mut parts, previous_end, comment.span.start);
+            parts.push(Doc::text(comment.text.trim_end()));
+            previous_end = Some(comment.span.end);
+        }
+        Ok(parts)
+    }
+
+    fn take_comment(&self, matches: impl Fn(&Comment) -> bool) -> Option<&Comment> {
+        let comment = self.comments.get(self.next_comment.get()).filter(|comment| matches(comment))?;
+        self.next_comment.set(self.next_comment.get() + 1);
+        Some(comment)
+    }
+
+    /// Comments before `offset` that haven't been placed yet, printed in front of the
+    /// token that starts there. Each keeps its own line if it had one in the source,
+    /// and stays inline with the token unless a line break followed it.
+    fn leading(&self, offset: usize) -> Doc {
+        let mut parts = Vec::new();
+        while let Some(comment) = self.take_comment(|comment| comment.span.start < offset) {
+            if self.starts_line(comment.span.start) {
+                parts.push(Doc::HardLine);
+            }
+            parts.push(Doc::text(comment.text.trim_end()));
+            if self.line(offset) > self.line(comment.span.end.saturating_sub(1)) {
+                parts.push(Doc::HardLine);
+            } else {
+                parts.push(Doc::text(" "));
+            }
+        }
+        Doc::Concat(parts)
+    }
+
+    /// Comments before `offset` that haven't been placed yet, printed after the code
+    /// ending at `end`: those on its line trail it, the rest go on lines of their own.
+    fn trailing(&self, end: usize, offset: usize) -> Vec<Doc> {
+        let mut parts = Vec::new();
+        while let Some(comment) = self.take_comment(|comment| comment.span.start < offset) {
+            if self.line(comment.span.start) == self.line(end.saturating_sub(1)) {
+                parts.push(Doc::text(" "));
+            } else {
+                parts.push(Doc::HardLine);
+            }
+            parts.push(Doc::text(comment.text.trim_end()));
+        }
+        parts
+    }
+
+    /// Whether only whitespace comes before `offset` on its line.
+    fn starts_line(&self, offset: usize) -> bool {
+        let line_start = self.source_map.line_span(self.line(offset)).start;
+        self.source_map.source()[line_start..offset].trim_ascii().is_empty()
+    }
+
+    /// Offset of the token after `offset`, skipping the whitespace and comments
+    /// before it.
+    fn token_after(&self, mut offset: usize) -> usize {
+        let source = self.source_map.source();
+        let mut comments = self.comments[self.next_comment.get()..].iter();
+        loop {
+            offset = source.len() - source[offset..].trim_ascii_start().len();
+            match comments.find(|comment| comment.span.start >= offset) {
+                Some(comment) if comment.span.start == offset => offset = comment.span.end,
+                _ => return offset,
+            }
+        }
+    }
+
+    /// Starts a new line unless this is the first thing in the sequence, keeping one
+    /// blank line if the source had any.
+    fn separate(&self, parts: &mut Vec<Doc>, previous_end: Option<usize>, next_start: usize) {
+        if let Some(previous_end) = previous_end {
+            parts.push(Doc::HardLine);
+            if self.line(next_start) > self.line(previous_end.saturating_sub(1)) + 1 {
+                parts.push(Doc::HardLine);
+            }
+        }
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.source_map.location(offset).line
+    }
+
+    /// `{ statements }`, where `span` covers the braces.
+    fn block(&self, statements: &[Stmt], span: Span) -> Result<Doc, LoxError> {
+        let body = self.sequence(statements, Stmt::span, |stmt| stmt.accept(self), span.end.saturating_sub(1))?;
+        Ok(braced(body))
+    }
+
+    /// The body of an `if`, `while` or `for`: a block stays on the same line, any other
+    /// statement moves to the next line if it doesn't fit.
+    fn body(&self, stmt: &Stmt) -> Result<Doc, LoxError> {
+        let doc = Doc::Concat(vec![self.leading(stmt.span().start), stmt.accept(self)?]);
+        Ok(match stmt {
+            Stmt::Block(_) => Doc::Concat(vec![Doc::text(" "), doc]),
+            _ => Doc::group(Doc::nest(Doc::Concat(vec![Doc::Line, doc]))),
+        })
+    }
+
+    /// Prints a `while` loop the parser desugared from `for` back as the `for` it was.
+    fn for_loop(&self, initializer: Option<&Stmt>, while_stmt: &WhileStmt) -> Result<Doc, LoxError> {
+        let clauses = while_stmt.desugared_for.expect("only desugared loops are printed as for");
+        let (body, increment) = match while_stmt.body.as_ref() {
+            Stmt::Block(block) if clauses.increment => match block.statements.as_slice() {
+                [body, Stmt::Expression(increment)] => (body, Some(&increment.expression)),
+                _ => unreachable!("the parser appends the increment to the body"),
+            },
+            body => (body, None),
+        };
+
+        let mut parts = vec![Doc::text("for (")];
+        match initializer {
+            Some(initializer) => parts.push(initializer.accept(self)?),
+            None => parts.push(Doc::text(";")),
+        }
+        if clauses.condition {
+            parts.push(Doc::text(" "));
+            parts.push(self.doc(&while_stmt.condition)?);
+        }
+        parts.push(Doc::text(";"));
+        if let Some(increment) = increment {
+            parts.push(Doc::text(" "));
+            parts.push(self.doc(increment)?);
+        }
+        parts.push(Doc::text(")"));
+        parts.push(self.body(body)?);
+        Ok(Doc::Concat(parts))
+    }
+
+    /// A function or method from its name on.
+    fn function(&self, stmt: &FunctionStmt) -> Result<Doc, LoxError> {
+        let name = self.name(&stmt.name);
+        let params = stmt.params.iter().map(|param| self.name(param)).collect();
+        let body = self.sequence(&stmt.body, Stmt::span, |stmt| stmt.accept(self), stmt.span.end.saturating_sub(1))?;
+        Ok(Doc::Concat(vec![
+            name,
+            Doc::parenthesized(params),
+            Doc::text(" "),
+            braced(body),
+        ]))
+    }
+
+    fn binary(&self, left: &Expr, operator: &str, right: &Expr) -> Result<Doc, LoxError> {
+        Ok(Doc::group(Doc::Concat(vec![
+            self.doc(left)?,
+            Doc::text(&format!(" {operator}")),
+            Doc::nest(Doc::Concat(vec![Doc::Line, self.doc(right)?])),
+        ])))
+    }
+}
+
+fn braced(body: Vec<Doc>) -> Doc {
+    if body.is_empty() {
+        return Doc::text("{}");
+    }
+    Doc::Concat(vec![
+        Doc::text("{"),
+        Doc::nest(Doc::Concat(vec![Doc::HardLine, Doc::Concat(body)])),
+        Doc::HardLine,
+        Doc::text("}"),
+    ])
+}
+
+impl Visitor<Doc> for Formatter<'_> {
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<Doc, LoxError> {
+        self.binary(&expr.left, &expr.operator.lexeme, &expr.right)
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<Doc, LoxError> {
+        Ok(Doc::Concat(vec![Doc::text("("), self.doc(&expr.expression)?, Doc::text(")")]))
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<Doc, LoxError> {
+        Ok(Doc::Text(expr.source_text()))
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<Doc, LoxError> {
+        Ok(Doc::Concat(vec![Doc::text(&expr.operator.lexeme), self.doc(&expr.right)?]))
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<Doc, LoxError> {
+        Ok(Doc::text(&expr.name.lexeme))
+    }
+
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<Doc, LoxError> {
+        Ok(Doc::Concat(vec![Doc::text(&format!("{} = ", expr.name.lexeme)), self.doc(&expr.value)?]))
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<Doc, LoxError> {
+        self.binary(&expr.left, &expr.operator.lexeme, &expr.right)
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<Doc, LoxError> {
+        let arguments = expr.arguments.iter().map(|argument| self.doc(argument)).collect::<Result<_, _>>()?;
+        Ok(Doc::Concat(vec![self.doc(&expr.callee)?, Doc::parenthesized(arguments)]))
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<Doc, LoxError> {
+        Ok(Doc::Concat(vec![self.doc(&expr.object)?, Doc::text(&format!(".{}", expr.name.lexeme))]))
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<Doc, LoxError> {
+        Ok(Doc::Concat(vec![
+            self.doc(&expr.object)?,
+            Doc::text(&format!(".{} = ", expr.name.lexeme)),
+            self.doc(&expr.value)?,
+        ]))
+    }
+
+    fn visit_this_expr(&self, _expr: &ThisExpr) -> Result<Doc, LoxError> {
+        Ok(Doc::text("this"))
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<Doc, LoxError> {
+        Ok(Doc::text(&format!("super.{}", expr.method.lexeme)))
+    }
+}
+
+impl StmtVisitor<Doc> for Formatter<'_> {
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<Doc, LoxError> {
+        Ok(Doc::Concat(vec![self.doc(&stmt.expression)?, Doc::text(";")]))
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<Doc, LoxError> {
+        Ok(Doc::Concat(vec![Doc::text("print "), self.doc(&stmt.expression)?, Doc::text(";")]))
+    }
+
+    fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<Doc, LoxError> {
+        let mut parts = vec![Doc::text("var "), self.name(&stmt.name)];
+        if let Some(initializer) = &stmt.initializer {
+            parts.push(Doc::text(" = "));
+            parts.push(self.doc(initializer)?);
+        }
+        parts.push(Doc::text(";"));
+        Ok(Doc::Concat(parts))
+    }
+
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<Doc, LoxError> {
+        match stmt.statements.as_slice() {
+            [initializer, Stmt::While(while_stmt)] if while_stmt.desugared_for.is_some_and(|clauses| clauses.initializer) => {
+                self.for_loop(Some(initializer), while_stmt)
+            },
+            _ => self.block(&stmt.statements, stmt.span),
+        }
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<Doc, LoxError> {
+        let mut parts = vec![Doc::text("if ("), self.doc(&stmt.condition)?, Doc::text(")"), self.body(&stmt.then_branch)?];
+        if let Some(else_branch) = &stmt.else_branch {
+            let then_end = stmt.then_branch.span().end;
+            let comments = self.trailing(then_end, self.token_after(then_end));
+            if comments.is_empty() && matches!(stmt.then_branch.as_ref(), Stmt::Block(_)) {
+                parts.push(Doc::text(" else"));
+            } else {
+                parts.extend(comments);
+                parts.push(Doc::HardLine);
+                parts.push(Doc::text("else"));
+            }
+            match else_branch.as_ref() {
+                Stmt::If(_) => parts.push(Doc::Concat(vec![
+                    Doc::text(" "),
+                    self.leading(else_branch.span().start),
+                    else_branch.accept(self)?,
+                ])),
+                _ => parts.push(self.body(else_branch)?),
+            }
+        }
+        Ok(Doc::Concat(parts))
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<Doc, LoxError> {
+        if stmt.desugared_for.is_some() {
+            return self.for_loop(None, stmt);
+        }
+        Ok(Doc::Concat(vec![Doc::text("while ("), self.doc(&stmt.condition)?, Doc::text(")"), self.body(&stmt.body)?]))
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<Doc, LoxError> {
+        Ok(Doc::Concat(vec![Doc::text("fun "), self.function(stmt)?]))
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<Doc, LoxError> {
+        Ok(match &stmt.value {
+            Some(value) => Doc::Concat(vec![Doc::text("return "), self.doc(value)?, Doc::text(";")]),
+            None => Doc::text("return;"),
+        })
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<Doc, LoxError> {
+        let mut header = vec![Doc::text("class "), self.name(&stmt.name)];
+        if let Some(superclass) = &stmt.superclass {
+            header.push(Doc::text(" < "));
+            header.push(self.name(&superclass.name));
+        }
+        header.push(Doc::text(" "));
+        let methods = self.sequence(&stmt.methods, |method| method.span, |method| self.function(method), stmt.span.end.saturating_sub(1))?;
+        header.push(braced(methods));
+        Ok(Doc::Concat(header))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(source: &str) -> String {
+        format_source(source, FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_canonical_layout() {
+        let source = "var  a=1;fun add(x,y){return x+y;}\nclass B<A{init(){this.x=-1;}}\nif(a>1)print a;else{print \"no\";}\nfor(var i=0;i<3;i=i+1)print i;\nwhile(true){}";
+        assert_eq!(
+            format(source),
+            "\
+var a = 1;
+fun add(x, y) {
+  return x + y;
+}
+class B < A {
+  init() {
+    this.x = -1;
+  }
+}
+if (a > 1) print a;
+else {
+  print \"no\";
+}
+for (var i = 0; i < 3; i = i + 1) print i;
+while (true) {}
+"
+        );
+    }
+
+    #[test]
+    fn test_recovers_desugared_for_loops() {
+        let source = "for(;i<3;)print i;\nfor(i=0;;i=i+1){print i;}\nfor(;;)print i;\n{var i=0;while(i<3)print i;}";
+        assert_eq!(
+            format(source),
+            "\
+for (; i < 3;) print i;
+for (i = 0;; i = i + 1) {
+  print i;
+}
+for (;;) print i;
+{
+  var i = 0;
+  while (i < 3) print i;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_preserves_comments_and_blank_lines() {
+        let source = "\
+// header
+
+var a = 1; // trailing
+/* before b */
+var b = 2;
+
+
+{
+  print a;
+  // end of block
+}
+// end of file
+";
+        assert_eq!(
+            format(source),
+            "\
+// header
+
+var a = 1; // trailing
+/* before b */
+var b = 2;
+
+{
+  print a;
+  // end of block
+}
+// end of file
+"
+        );
+    }
+
+    #[test]
+    fn test_comments_inside_if_else() {
+        let source = "\
+if (true) // after cond
+  print 1;
+else
+  print 2;
+print 3;
+if (a) {
+  print 1;
+} // then done
+else
+  // explain
+  print 2;
+";
+        assert_eq!(
+            format(source),
+            "\
+if (true) // after cond
+  print 1;
+else print 2;
+print 3;
+if (a) {
+  print 1;
+} // then done
+else
+  // explain
+  print 2;
+"
+        );
+    }
+
+    #[test]
+    fn test_inline_block_comments() {
+        let source = "var a = /* one */ 1 + /* two */ 2;\nfun f(x /* first */, y) { return /* r */ x; }\n";
+        assert_eq!(
+            format(source),
+            "var a = /* one */ 1 + /* two */ 2;\nfun f(x /* first */, y) {\n  return /* r */ x;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_breaks_long_lines() {
+        let options = FormatOptions {
+            indent: 4,
+            line_width: 20,
+        };
+        let formatted = format_source("print f(first, second, third);\nprint 1 + 2;", options).unwrap();
+        assert_eq!(formatted, "print f(\n    first,\n    second,\n    third\n);\nprint 1 + 2;\n");
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let source = "fun f() { return 1; } // after f\nclass A{m(a,b){if(a)return b;else if(b)return a;for(;;){print this.m(a,b,\"a very long string argument\",1.50);}}}";
+        let once = format(source);
+        assert_eq!(format(&once), once);
+        assert!(once.contains("1.5)"));
+        assert!(once.starts_with("fun f() {\n  return 1;\n} // after f\n"), "{once}");
+    }
+
+    #[test]
+    fn test_rejects_syntax_errors() {
+        assert!(format_source("print (1;", FormatOptions::default()).is_err());
+    }
+}
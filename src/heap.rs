@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use super::chunk::Function;
+use super::symbol::Symbol;
+use super::value::LoxString;
+
+/// Handle to an object in a [`Heap`]. Copying it copies the reference, not the object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjRef(usize);
+
+/// A value on the bytecode VM's stack. Unlike the tree-walker's `Value` it is
+/// `Copy`: everything bigger than a number lives in the heap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Obj(ObjRef),
+}
+
+impl Value {
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Nil => false,
+            Value::Bool(b) => *b,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<ObjRef>,
+}
+
+/// A captured variable. It points into the VM stack while the variable's scope is
+/// live, and holds the value itself once the scope has ended.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: Symbol,
+    /// Closures, including those inherited from the superclass.
+    pub methods: HashMap<Symbol, ObjRef>,
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: ObjRef,
+    pub fields: HashMap<Symbol, Value>,
+}
+
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Value,
+    pub method: ObjRef,
+}
+
+pub struct Native {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: fn(&[Value]) -> Value,
+}
+
+impl std::fmt::Debug for Native {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Native").field("name", &self.name).field("arity", &self.arity).finish()
+    }
+}
+
+#[derive(Debug)]
+pub enum Obj {
+    Str(LoxString),
+    Closure(Closure),
+    Upvalue(Upvalue),
+    Class(Class),
+    Instance(Instance),
+    BoundMethod(BoundMethod),
+    Native(Native),
+}
+
+impl Obj {
+    /// Approximate memory used by the object.
+    fn size(&self) -> usize {
+        size_of::<Obj>() + match self {
+            Obj::Str(LoxString::Interned(_)) => 0,
+            Obj::Str(LoxString::Owned(s)) => s.len(),
+            Obj::Closure(closure) => closure.upvalues.capacity() * size_of::<ObjRef>(),
+            Obj::Class(class) => class.methods.capacity() * size_of::<(Symbol, ObjRef)>(),
+            Obj::Instance(instance) => instance.fields.capacity() * size_of::<(Symbol, Value)>(),
+            Obj::Upvalue(_) | Obj::BoundMethod(_) | Obj::Native(_) => 0,
+        }
+    }
+}
+
+/// Heap size before the first collection.
+const FIRST_GC: usize = 1024 * 1024;
+/// After a collection, the next one happens once the heap has grown by this factor.
+const GC_HEAP_GROW_FACTOR: usize = 2;
+
+/// What one garbage collection did, as `--log-gc` reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GcStats {
+    /// Collections run so far, including this one.
+    pub collections: usize,
+    pub freed_objects: usize,
+    pub freed_bytes: usize,
+    pub live_objects: usize,
+    pub live_bytes: usize,
+    /// Heap size in bytes that triggers the next collection.
+    pub next_gc: usize,
+}
+
+impl fmt::Display for GcStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[gc {}] freed {} objects ({} bytes), {} live ({} bytes), next collection at {} bytes",
+            self.collections, self.freed_objects, self.freed_bytes, self.live_objects, self.live_bytes, self.next_gc
+        )
+    }
+}
+
+/// Garbage-collected store of every object the VM allocates.
+///
+/// Collection is mark-and-sweep with tri-colour marking: every object starts white;
+/// marking a root turns it gray by pushing it on the gray stack, and tracing a gray
+/// object's references turns it black. Once no gray objects are left, the white ones
+/// are unreachable and their slots are freed for reuse. The heap does not know the
+/// roots, so the VM decides when to call [`Heap::collect`], normally once
+/// [`Heap::should_collect`] says the heap has outgrown its threshold.
+///
+/// Compiled [`Function`]s are not heap objects: they are immutable and cannot form
+/// cycles, so closures share them through `Rc`.
+#[derive(Debug)]
+pub struct Heap {
+    objects: Vec<Option<Obj>>,
+    /// Set for gray and black objects during a collection.
+    marks: Vec<bool>,
+    free: Vec<usize>,
+    gray: Vec<ObjRef>,
+    bytes_allocated: usize,
+    next_gc: usize,
+    /// Collect before every allocation, to flush out objects the VM forgot to root.
+    stress: bool,
+    collections: usize,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self {
+            objects: Vec::new(),
+            marks: Vec::new(),
+            free: Vec::new(),
+            gray: Vec::new(),
+            bytes_allocated: 0,
+            next_gc: FIRST_GC,
+            stress: false,
+            collections: 0,
+        }
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_stress(mut self, stress: bool) -> Self {
+        self.stress = stress;
+        self
+    }
+
+    /// Whether the VM should collect before allocating again.
+    pub fn should_collect(&self) -> bool {
+        self.stress || self.bytes_allocated > self.next_gc
+    }
+
+    pub fn alloc(&mut self, obj: Obj) -> ObjRef {
+        self.bytes_allocated += obj.size();
+        match self.free.pop() {
+            Some(index) => {
+                self.objects[index] = Some(obj);
+                ObjRef(index)
+            },
+            None => {
+                self.objects.push(Some(obj));
+                self.marks.push(false);
+                ObjRef(self.objects.len() - 1)
+            },
+        }
+    }
+
+    /// Number of live objects.
+    pub fn len(&self) -> usize {
+        self.objects.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, obj: ObjRef) -> &Obj {
+        self.objects[obj.0].as_ref().expect("object was freed while still reachable")
+    }
+
+    pub fn get_mut(&mut self, obj: ObjRef) -> &mut Obj {
+        self.objects[obj.0].as_mut().expect("object was freed while still reachable")
+    }
+
+    /// Frees every object not reachable from `roots`.
+    pub fn collect(&mut self, roots: &[Value]) -> GcStats {
+        for &root in roots {
+            self.mark_value(root);
+        }
+        while let Some(obj) = self.gray.pop() {
+            self.blacken(obj);
+        }
+        let (freed_objects, freed_bytes) = self.sweep();
+
+        self.collections += 1;
+        self.next_gc = (self.bytes_allocated * GC_HEAP_GROW_FACTOR).max(FIRST_GC);
+        GcStats {
+            collections: self.collections,
+            freed_objects,
+            freed_bytes,
+            live_objects: self.len(),
+            live_bytes: self.bytes_allocated,
+            next_gc: self.next_gc,
+        }
+    }
+
+    fn mark_value(&mut self, value: Value) {
+        if let Value::Obj(obj) = value {
+            self.mark_object(obj);
+        }
+    }
+
+    /// Turns a white object gray.
+    fn mark_object(&mut self, obj: ObjRef) {
+        if !self.marks[obj.0] {
+            self.marks[obj.0] = true;
+            self.gray.push(obj);
+        }
+    }
+
+    /// Marks everything a gray object references, turning it black.
+    fn blacken(&mut self, obj: ObjRef) {
+        let mut references = Vec::new();
+        match self.get(obj) {
+            Obj::Str(_) | Obj::Native(_) | Obj::Upvalue(Upvalue::Open(_)) => {},
+            Obj::Upvalue(Upvalue::Closed(value)) => references.push(*value),
+            Obj::Closure(closure) => references.extend(closure.upvalues.iter().map(|&upvalue| Value::Obj(upvalue))),
+            Obj::Class(class) => references.extend(class.methods.values().map(|&method| Value::Obj(method))),
+            Obj::Instance(instance) => {
+                references.push(Value::Obj(instance.class));
+                references.extend(instance.fields.values().copied());
+            },
+            Obj::BoundMethod(bound) => references.extend([bound.receiver, Value::Obj(bound.method)]),
+        }
+        for value in references {
+            self.mark_value(value);
+        }
+    }
+
+    /// Frees the white objects and whitens the black ones for the next collection.
+    /// Objects grow after they are allocated, so the heap size is recounted from the
+    /// survivors.
+    fn sweep(&mut self) -> (usize, usize) {
+        let (mut freed_objects, mut freed_bytes, mut live_bytes) = (0, 0, 0);
+        for (index, slot) in self.objects.iter_mut().enumerate() {
+            let Some(obj) = slot else { continue };
+            if std::mem::take(&mut self.marks[index]) {
+                live_bytes += obj.size();
+            } else {
+                freed_objects += 1;
+                freed_bytes += obj.size();
+                *slot = None;
+                self.free.push(index);
+            }
+        }
+        self.bytes_allocated = live_bytes;
+        (freed_objects, freed_bytes)
+    }
+
+    pub fn str(&self, value: Value) -> Option<&str> {
+        match value {
+            Value::Obj(obj) => match self.get(obj) {
+                Obj::Str(s) => Some(s),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn closure(&self, obj: ObjRef) -> &Closure {
+        match self.get(obj) {
+            Obj::Closure(closure) => closure,
+            other => panic!("expected a closure, found {other:?}"),
+        }
+    }
+
+    pub fn class(&self, obj: ObjRef) -> &Class {
+        match self.get(obj) {
+            Obj::Class(class) => class,
+            other => panic!("expected a class, found {other:?}"),
+        }
+    }
+
+    /// Lox equality: strings compare by content, other objects by identity.
+    pub fn values_equal(&self, a: Value, b: Value) -> bool {
+        match (self.str(a), self.str(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == b,
+        }
+    }
+
+    /// Formats `value` the way `print` shows it, matching the tree-walking interpreter.
+    pub fn display(&self, value: Value) -> String {
+        match value {
+            Value::Nil => "nil".to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Obj(obj) => match self.get(obj) {
+                Obj::Str(s) => s.to_string(),
+                Obj::Closure(closure) => closure.function.to_string(),
+                Obj::Upvalue(_) => "upvalue".to_string(),
+                Obj::Class(class) => class.name.to_string(),
+                Obj::Instance(instance) => format!("{} instance", self.class(instance.class).name),
+                Obj::BoundMethod(bound) => self.closure(bound.method).function.to_string(),
+                Obj::Native(_) => "<native fn>".to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_frees_unreachable_cycles() {
+        let mut heap = Heap::new();
+        let class = heap.alloc(Obj::Class(Class { name: Symbol::intern("A"), methods: HashMap::new() }));
+        let instance = heap.alloc(Obj::Instance(Instance { class, fields: HashMap::new() }));
+        let orphan = heap.alloc(Obj::Str("garbage".into()));
+        let method = heap.alloc(Obj::Closure(Closure { function: Rc::new(Function::new("m", 0)), upvalues: Vec::new() }));
+        // The instance references itself through a bound method stored in a field.
+        let bound = heap.alloc(Obj::BoundMethod(BoundMethod { receiver: Value::Obj(instance), method }));
+        if let Obj::Instance(instance) = heap.get_mut(instance) {
+            instance.fields.insert(Symbol::intern("self"), Value::Obj(bound));
+        }
+
+        let stats = heap.collect(&[Value::Obj(instance)]);
+        assert_eq!((stats.freed_objects, stats.live_objects), (1, 4));
+        assert_eq!(heap.alloc(Obj::Str("reused".into())), orphan);
+
+        let stats = heap.collect(&[]);
+        assert_eq!((stats.freed_objects, stats.live_objects, stats.live_bytes), (5, 0, 0));
+        assert_eq!(stats.collections, 2);
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn test_strings_compare_by_content() {
+        let mut heap = Heap::new();
+        let a = Value::Obj(heap.alloc(Obj::Str("lox".into())));
+        let b = Value::Obj(heap.alloc(Obj::Str("lox".into())));
+        let class = Value::Obj(heap.alloc(Obj::Class(Class { name: Symbol::intern("A"), methods: HashMap::new() })));
+        assert!(heap.values_equal(a, b));
+        assert!(heap.values_equal(class, class));
+        assert!(!heap.values_equal(a, class));
+        assert_eq!(heap.display(class), "A");
+        assert_eq!(heap.display(Value::Number(2.5)), "2.5");
+    }
+}
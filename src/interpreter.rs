@@ -0,0 +1,589 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
+
+use super::ast::*;
+use super::callable::{LoxFunction, NativeFunction};
+use super::class::{LoxClass, LoxInstance};
+use super::environment::Environment;
+use super::errors::LoxError;
+use super::symbol::Symbol;
+use super::token::Token;
+use super::token_type::TokenType;
+use super::value::Value;
+use super::vm::FRAMES_MAX;
+
+/// Stack space that must be left before a call, and how much more to allocate
+/// when there isn't. Each Lox call takes several nested Rust calls, so deep
+/// recursion would overflow the native stack well before [`FRAMES_MAX`] calls.
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_GROWTH: usize = 4 * 1024 * 1024;
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: RefCell<Rc<RefCell<Environment>>>,
+    locals: RefCell<HashMap<ExprId, usize>>,
+    output: RefCell<Box<dyn Write>>,
+    /// Calls currently executing, to stop runaway recursion before Rust's own stack
+    /// overflows.
+    call_depth: Cell<usize>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::with_output(Box::new(std::io::stdout()))
+    }
+
+    /// Creates an interpreter whose `print` statements write to `output` instead of stdout.
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define(Symbol::CLOCK, Value::Callable(Rc::new(NativeFunction {
+            name: "clock",
+            arity: 0,
+            function: |_| {
+                let elapsed = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default();
+                Value::Number(elapsed.as_secs_f64())
+            },
+        })));
+
+        Self {
+            environment: RefCell::new(globals.clone()),
+            globals,
+            locals: RefCell::new(HashMap::new()),
+            output: RefCell::new(output),
+            call_depth: Cell::new(0),
+        }
+    }
+
+    /// Executes `statements` in order. If the last one is an expression statement its
+    /// value is returned, which lets the REPL echo results.
+    pub fn interpret(&self, statements: &[Stmt]) -> Result<Option<Value>, LoxError> {
+        let Some((last, rest)) = statements.split_last() else {
+            return Ok(None);
+        };
+        for statement in rest {
+            self.execute(statement)?;
+        }
+        match last {
+            Stmt::Expression(stmt) => Ok(Some(self.evaluate(&stmt.expression)?)),
+            _ => {
+                self.execute(last)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Evaluates a single expression against the current global state.
+    pub fn interpret_expression(&self, expr: &Expr) -> Result<Value, LoxError> {
+        self.evaluate(expr)
+    }
+
+    /// Records that the variable referenced by expression `id` lives `depth`
+    /// environments above the one it is evaluated in.
+    pub fn resolve(&self, id: ExprId, depth: usize) {
+        self.locals.borrow_mut().insert(id, depth);
+    }
+
+    fn evaluate(&self, expr: &Expr) -> Result<Value, LoxError> {
+        expr.accept(self)
+    }
+
+    fn look_up_variable(&self, name: &Token, id: ExprId) -> Result<Value, LoxError> {
+        match self.locals.borrow().get(&id) {
+            Some(&distance) => self.environment.borrow().borrow().get_at(distance, name.lexeme.symbol())
+                .ok_or_else(|| LoxError::UndefinedVariable(name.clone())),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn execute(&self, stmt: &Stmt) -> Result<(), LoxError> {
+        stmt.accept(self)
+    }
+
+    pub(crate) fn execute_block(&self, statements: &[Stmt], environment: Environment) -> Result<(), LoxError> {
+        self.in_environment(environment, || statements.iter().try_for_each(|statement| self.execute(statement)))
+    }
+
+    /// Runs `f` with `environment` as the current scope, restoring the previous
+    /// scope afterwards even if `f` fails.
+    fn in_environment<T>(&self, environment: Environment, f: impl FnOnce() -> Result<T, LoxError>) -> Result<T, LoxError> {
+        let previous = self.environment.replace(Rc::new(RefCell::new(environment)));
+        let result = f();
+        self.environment.replace(previous);
+        result
+    }
+
+    fn number_operand(&self, operator: &Token, operand: Value) -> Result<f64, LoxError> {
+        match operand {
+            Value::Number(n) => Ok(n),
+            _ => Err(LoxError::OperandMustBeNumber(operator.clone())),
+        }
+    }
+
+    fn number_operands(&self, operator: &Token, left: Value, right: Value) -> Result<(f64, f64), LoxError> {
+        match (left, right) {
+            (Value::Number(l), Value::Number(r)) => Ok((l, r)),
+            _ => Err(LoxError::OperandsMustBeNumbers(operator.clone())),
+        }
+    }
+}
+
+impl Visitor<Value> for Interpreter {
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<Value, LoxError> {
+        let left = self.evaluate(&expr.left)?;
+        let right = self.evaluate(&expr.right)?;
+        let operator = &expr.operator;
+
+        match operator.token_type {
+            TokenType::EqualEqual => Ok(Value::Bool(left == right)),
+            TokenType::BangEqual => Ok(Value::Bool(left != right)),
+            TokenType::Plus => match (left, right) {
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
+                (Value::Str(l), Value::Str(r)) => Ok(Value::Str(format!("{l}{r}").into())),
+                _ => Err(LoxError::OperandsMustBeNumbersOrStrings(operator.clone())),
+            },
+            TokenType::Minus => {
+                let (l, r) = self.number_operands(operator, left, right)?;
+                Ok(Value::Number(l - r))
+            },
+            TokenType::Star => {
+                let (l, r) = self.number_operands(operator, left, right)?;
+                Ok(Value::Number(l * r))
+            },
+            TokenType::Slash => {
+                let (l, r) = self.number_operands(operator, left, right)?;
+                Ok(Value::Number(l / r))
+            },
+            TokenType::Greater => {
+                let (l, r) = self.number_operands(operator, left, right)?;
+                Ok(Value::Bool(l > r))
+            },
+            TokenType::GreaterEqual => {
+                let (l, r) = self.number_operands(operator, left, right)?;
+                Ok(Value::Bool(l >= r))
+            },
+            TokenType::Less => {
+                let (l, r) = self.number_operands(operator, left, right)?;
+                Ok(Value::Bool(l < r))
+            },
+            TokenType::LessEqual => {
+                let (l, r) = self.number_operands(operator, left, right)?;
+                Ok(Value::Bool(l <= r))
+            },
+            _ => Err(LoxError::InvalidOperator(operator.clone())),
+        }
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<Value, LoxError> {
+        self.evaluate(&expr.expression)
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<Value, LoxError> {
+        Ok(Value::from(&expr.value))
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<Value, LoxError> {
+        let right = self.evaluate(&expr.right)?;
+
+        match expr.operator.token_type {
+            TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
+            TokenType::Minus => {
+                let n = self.number_operand(&expr.operator, right)?;
+                Ok(Value::Number(-n))
+            },
+            _ => Err(LoxError::InvalidOperator(expr.operator.clone())),
+        }
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<Value, LoxError> {
+        self.look_up_variable(&expr.name, expr.id)
+    }
+
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<Value, LoxError> {
+        let value = self.evaluate(&expr.value)?;
+        match self.locals.borrow().get(&expr.id) {
+            Some(&distance) => self.environment.borrow().borrow_mut().assign_at(distance, &expr.name, value.clone())?,
+            None => self.globals.borrow_mut().assign(&expr.name, value.clone())?,
+        }
+        Ok(value)
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<Value, LoxError> {
+        let left = self.evaluate(&expr.left)?;
+
+        if expr.operator.token_type == TokenType::Or {
+            if left.is_truthy() {
+                return Ok(left);
+            }
+        } else if !left.is_truthy() {
+            return Ok(left);
+        }
+
+        self.evaluate(&expr.right)
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<Value, LoxError> {
+        let callee = self.evaluate(&expr.callee)?;
+
+        let mut arguments = Vec::with_capacity(expr.arguments.len());
+        for argument in &expr.arguments {
+            arguments.push(self.evaluate(argument)?);
+        }
+
+        let arity = match &callee {
+            Value::Callable(function) => function.arity(),
+            Value::Class(class) => class.arity(),
+            _ => return Err(LoxError::NotCallable(expr.paren.clone())),
+        };
+        if arguments.len() != arity {
+            return Err(LoxError::ArityMismatch(expr.paren.clone(), arity, arguments.len()));
+        }
+        // The VM's frame limit includes the frame of the top-level script.
+        let depth = self.call_depth.get();
+        if depth + 1 == FRAMES_MAX {
+            return Err(LoxError::StackOverflow(expr.paren.clone()));
+        }
+        self.call_depth.set(depth + 1);
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, || match callee {
+            Value::Callable(function) => function.call(self, arguments),
+            Value::Class(class) => LoxClass::instantiate(&class, self, arguments),
+            _ => unreachable!("checked above"),
+        });
+        self.call_depth.set(depth);
+        result
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<Value, LoxError> {
+        match self.evaluate(&expr.object)? {
+            Value::Instance(instance) => LoxInstance::get(&instance, &expr.name),
+            _ => Err(LoxError::OnlyInstancesHaveProperties(expr.name.clone())),
+        }
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<Value, LoxError> {
+        let Value::Instance(instance) = self.evaluate(&expr.object)? else {
+            return Err(LoxError::OnlyInstancesHaveFields(expr.name.clone()));
+        };
+        let value = self.evaluate(&expr.value)?;
+        instance.borrow_mut().set(&expr.name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_this_expr(&self, expr: &ThisExpr) -> Result<Value, LoxError> {
+        self.look_up_variable(&expr.keyword, expr.id)
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<Value, LoxError> {
+        let Some(&distance) = self.locals.borrow().get(&expr.id) else {
+            return Err(LoxError::UndefinedVariable(expr.keyword.clone()));
+        };
+        let environment = self.environment.borrow().clone();
+        // `this` is always bound in the environment just inside the one holding `super`.
+        let Some(Value::Class(superclass)) = environment.borrow().get_at(distance, Symbol::SUPER) else {
+            return Err(LoxError::UndefinedVariable(expr.keyword.clone()));
+        };
+        let Some(Value::Instance(object)) = environment.borrow().get_at(distance - 1, Symbol::THIS) else {
+            return Err(LoxError::UndefinedVariable(expr.keyword.clone()));
+        };
+
+        match superclass.find_method(expr.method.lexeme.symbol()) {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(object)))),
+            None => Err(LoxError::UndefinedProperty(expr.method.clone())),
+        }
+    }
+}
+
+impl StmtVisitor<()> for Interpreter {
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<(), LoxError> {
+        self.evaluate(&stmt.expression)?;
+        Ok(())
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<(), LoxError> {
+        let value = self.evaluate(&stmt.expression)?;
+        writeln!(self.output.borrow_mut(), "{value}").map_err(LoxError::Io)
+    }
+
+    fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<(), LoxError> {
+        let value = match &stmt.initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+        self.environment.borrow().borrow_mut().define(stmt.name.lexeme.symbol(), value);
+        Ok(())
+    }
+
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<(), LoxError> {
+        let enclosing = self.environment.borrow().clone();
+        self.execute_block(&stmt.statements, Environment::with_enclosing(enclosing))
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<(), LoxError> {
+        if self.evaluate(&stmt.condition)?.is_truthy() {
+            self.execute(&stmt.then_branch)
+        } else if let Some(else_branch) = &stmt.else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<(), LoxError> {
+        loop {
+            if !self.evaluate(&stmt.condition)?.is_truthy() {
+                break;
+            }
+            self.execute(&stmt.body)?;
+        }
+        Ok(())
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<(), LoxError> {
+        let closure = self.environment.borrow().clone();
+        let function = LoxFunction::new(stmt.clone(), closure, false);
+        self.environment.borrow().borrow_mut().define(stmt.name.lexeme.symbol(), Value::Callable(Rc::new(function)));
+        Ok(())
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<(), LoxError> {
+        let value = match &stmt.value {
+            Some(value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+        Err(LoxError::Return(value))
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<(), LoxError> {
+        let superclass = match &stmt.superclass {
+            Some(superclass) => match self.visit_variable_expr(superclass)? {
+                Value::Class(class) => Some(class),
+                _ => return Err(LoxError::SuperclassMustBeClass(superclass.name.clone())),
+            },
+            None => None,
+        };
+
+        self.environment.borrow().borrow_mut().define(stmt.name.lexeme.symbol(), Value::Nil);
+
+        let enclosing = self.environment.borrow().clone();
+        let method_environment = match &superclass {
+            Some(superclass) => {
+                let mut environment = Environment::with_enclosing(enclosing.clone());
+                environment.define(Symbol::SUPER, Value::Class(superclass.clone()));
+                Rc::new(RefCell::new(environment))
+            },
+            None => enclosing.clone(),
+        };
+
+        let methods: HashMap<Symbol, Rc<LoxFunction>> = stmt.methods
+            .iter()
+            .map(|method| {
+                let is_initializer = method.name.lexeme.symbol() == Symbol::INIT;
+                let function = LoxFunction::new(method.clone(), method_environment.clone(), is_initializer);
+                (method.name.lexeme.symbol(), Rc::new(function))
+            })
+            .collect();
+
+        let class = LoxClass::new(stmt.name.lexeme.symbol(), superclass, methods);
+        let result = enclosing.borrow_mut().assign(&stmt.name, Value::Class(Rc::new(class)));
+        result
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::Resolver;
+    use crate::span::Span;
+    use crate::tests::Output;
+    use crate::token::*;
+
+    fn execute(source: &str) -> Result<String, LoxError> {
+        let statements = crate::parse(source.as_bytes()).unwrap();
+        let output = Output::default();
+        let interpreter = Interpreter::with_output(Box::new(output.clone()));
+        Resolver::new(&interpreter).resolve(&statements).unwrap();
+        interpreter.interpret(&statements)?;
+        Ok(output.take())
+    }
+
+    fn number(n: f64) -> Expr {
+        Expr::Literal(LiteralExpr::new(Some(Literal::Number(n)), Span::default()))
+    }
+
+    fn string(s: &str) -> Expr {
+        Expr::Literal(LiteralExpr::new(Some(Literal::Str(s.into())), Span::default()))
+    }
+
+    fn binary(left: Expr, token_type: TokenType, lexeme: &str, right: Expr) -> Expr {
+        Expr::Binary(BinaryExpr::new(left, Token::new(token_type, lexeme.to_string(), None, 1), right))
+    }
+
+    fn unary(token_type: TokenType, lexeme: &str, right: Expr) -> Expr {
+        Expr::Unary(UnaryExpr::new(Token::new(token_type, lexeme.to_string(), None, 1), right))
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let expr = binary(
+            unary(TokenType::Minus, "-", number(2.0)),
+            TokenType::Star,
+            "*",
+            Expr::Grouping(GroupingExpr::new(binary(number(3.0), TokenType::Plus, "+", number(4.0)), Span::default())),
+        );
+        assert_eq!(Interpreter::new().evaluate(&expr).unwrap(), Value::Number(-14.0));
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        let expr = binary(string("foo"), TokenType::Plus, "+", string("bar"));
+        assert_eq!(Interpreter::new().evaluate(&expr).unwrap().to_string(), "foobar");
+    }
+
+    #[test]
+    fn test_truthiness() {
+        let nil = Expr::Literal(LiteralExpr::new(None, Span::default()));
+        assert_eq!(Interpreter::new().evaluate(&unary(TokenType::Bang, "!", nil)).unwrap(), Value::Bool(true));
+        assert_eq!(Interpreter::new().evaluate(&unary(TokenType::Bang, "!", number(0.0))).unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_equality() {
+        let nil = || Expr::Literal(LiteralExpr::new(None, Span::default()));
+        assert_eq!(Interpreter::new().evaluate(&binary(nil(), TokenType::EqualEqual, "==", nil())).unwrap(), Value::Bool(true));
+        assert_eq!(Interpreter::new().evaluate(&binary(number(1.0), TokenType::EqualEqual, "==", string("1"))).unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_type_error() {
+        let expr = binary(number(1.0), TokenType::Minus, "-", string("a"));
+        match Interpreter::new().evaluate(&expr) {
+            Err(LoxError::OperandsMustBeNumbers(token)) => assert_eq!(token.lexeme, "-"),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_block_scoping() {
+        let output = execute(r#"var a = "global"; { var a = "local"; print a; } print a;"#).unwrap();
+        assert_eq!(output, "local\nglobal\n");
+    }
+
+    #[test]
+    fn test_assignment_reaches_enclosing_scope() {
+        let output = execute("var a; { a = 2; } print a;").unwrap();
+        assert_eq!(output, "2\n");
+    }
+
+    #[test]
+    fn test_logical_returns_operand() {
+        let nil = Expr::Literal(LiteralExpr::new(None, Span::default()));
+        let or = Expr::Logical(LogicalExpr::new(nil, Token::new(TokenType::Or, "or".to_string(), None, 1), string("yes")));
+        assert_eq!(Interpreter::new().evaluate(&or).unwrap(), Value::Str("yes".into()));
+
+        let and = Expr::Logical(LogicalExpr::new(number(0.0), Token::new(TokenType::And, "and".to_string(), None, 1), string("yes")));
+        assert_eq!(Interpreter::new().evaluate(&and).unwrap(), Value::Str("yes".into()));
+    }
+
+    #[test]
+    fn test_logical_short_circuits() {
+        let output = execute(r#"false and undefined; print "ok";"#).unwrap();
+        assert_eq!(output, "ok\n");
+    }
+
+    #[test]
+    fn test_while_and_if() {
+        let output = execute(r#"
+            var i = 0;
+            while (i < 3) {
+                if (i == 1) print "one"; else print i;
+                i = i + 1;
+            }
+        "#).unwrap();
+        assert_eq!(output, "0\none\n2\n");
+    }
+
+    #[test]
+    fn test_closure_captures_environment() {
+        let output = execute("
+            fun makeCounter() {
+                var i = 0;
+                fun count() { i = i + 1; return i; }
+                return count;
+            }
+            var counter = makeCounter();
+            counter();
+            print counter();
+            print counter;
+        ").unwrap();
+        assert_eq!(output, "2\n<fn count>\n");
+    }
+
+    #[test]
+    fn test_arity_mismatch() {
+        let result = execute("fun f(a) {} f();");
+        assert!(matches!(result, Err(LoxError::ArityMismatch(_, 1, 0))));
+    }
+
+    #[test]
+    fn test_calling_non_callable() {
+        let result = execute(r#""text"();"#);
+        assert!(matches!(result, Err(LoxError::NotCallable(_))));
+    }
+
+    #[test]
+    fn test_class_with_initializer_and_super_call() {
+        let output = execute(r#"
+            class A { greet() { return "hello "; } }
+            class B < A {
+                init(name) { this.name = name; }
+                greet() { return super.greet() + this.name; }
+            }
+            var b = B("lox");
+            print b.greet();
+            print b;
+            print b.init("again");
+        "#).unwrap();
+        assert_eq!(output, "hello lox\nB instance\nB instance\n");
+    }
+
+    #[test]
+    fn test_undefined_property() {
+        let result = execute("class A {} A().missing;");
+        assert!(matches!(result, Err(LoxError::UndefinedProperty(token)) if token.lexeme == "missing"));
+    }
+
+    #[test]
+    fn test_superclass_must_be_class() {
+        let result = execute("var A = 1; class B < A {}");
+        assert!(matches!(result, Err(LoxError::SuperclassMustBeClass(token)) if token.lexeme == "A"));
+    }
+
+    #[test]
+    fn test_closure_binds_to_declaration_scope() {
+        let output = execute(r#"
+            var a = "global";
+            {
+                fun show() { print a; }
+                show();
+                var a = "block";
+                show();
+            }
+        "#).unwrap();
+        assert_eq!(output, "global\nglobal\n");
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let result = execute("print missing;");
+        assert!(matches!(result, Err(LoxError::UndefinedVariable(token)) if token.lexeme == "missing"));
+    }
+}
@@ -1,21 +1,387 @@
-mod errors;
-mod token_type;
-mod scanner;
-mod token;
-mod ast;
-mod ast_printer;
-mod parser;
+pub mod errors;
+pub mod token_type;
+pub mod scanner;
+pub mod token;
+pub mod symbol;
+pub mod ast;
+pub mod ast_printer;
+pub mod rpn_printer;
+pub mod dot_printer;
+pub mod parser;
+pub mod value;
+pub mod interpreter;
+pub mod environment;
+pub mod callable;
+pub mod class;
+pub mod resolver;
+pub mod span;
+pub mod chunk;
+pub mod heap;
+pub mod compiler;
+pub mod vm;
+pub mod disassembler;
+pub mod loxc;
+pub mod diagnostics;
+pub mod codes;
+pub mod formatter;
+pub mod cst;
+pub mod cst_parser;
 use errors::*;
+use std::io::Write;
 use std::path::Path;
+use std::rc::Rc;
 use ast::*;
+use chunk::Function;
+use compiler::Compiler;
+use scanner::Scanner;
+use parser::Parser;
+use interpreter::Interpreter;
+use resolver::Resolver;
+use span::SourceMap;
+use value::Value;
+use vm::Vm;
 
+/// Outcome of running a piece of Lox source through the whole pipeline.
+#[derive(Debug)]
+pub enum RunResult {
+    Success(Option<Value>),
+    StaticErrors(Vec<StaticError>),
+    RuntimeError(LoxError),
+}
+
+impl RunResult {
+    /// Conventional `sysexits.h` code for this outcome: 65 for bad input, 70 for a runtime failure.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunResult::Success(_) => 0,
+            RunResult::StaticErrors(_) => 65,
+            RunResult::RuntimeError(_) => 70,
+        }
+    }
+}
+
+/// Which engine executes programs. Both share the scanner, parser and resolver and
+/// print the same output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Evaluates the AST directly.
+    #[default]
+    TreeWalk,
+    /// Compiles to bytecode and runs it on a stack machine.
+    Vm,
+}
+
+enum Engine {
+    TreeWalk(Interpreter),
+    Vm(Vm),
+}
+
+/// A long-lived interpreter session; state persists between calls to [`Lox::run`].
+pub struct Lox {
+    engine: Engine,
+}
+
+impl Lox {
+    pub fn new() -> Self {
+        Self::with_backend(Backend::TreeWalk)
+    }
+
+    pub fn with_backend(backend: Backend) -> Self {
+        Self::with_output(backend, Box::new(std::io::stdout()))
+    }
+
+    /// Creates a session whose `print` statements write to `output` instead of stdout.
+    pub fn with_output(backend: Backend, output: Box<dyn Write>) -> Self {
+        let engine = match backend {
+            Backend::TreeWalk => Engine::TreeWalk(Interpreter::with_output(output)),
+            Backend::Vm => Engine::Vm(Vm::with_output(output)),
+        };
+        Self { engine }
+    }
+
+    /// Makes the VM collect garbage before every allocation. Has no effect on the
+    /// tree-walking backend, whose values are reference counted.
+    pub fn with_stress_gc(mut self) -> Self {
+        if let Engine::Vm(vm) = self.engine {
+            self.engine = Engine::Vm(vm.with_stress_gc());
+        }
+        self
+    }
+
+    /// Makes the VM write statistics to `log` after each garbage collection. Has no
+    /// effect on the tree-walking backend.
+    pub fn with_gc_log(mut self, log: Box<dyn Write>) -> Self {
+        if let Engine::Vm(vm) = self.engine {
+            self.engine = Engine::Vm(vm.with_gc_log(log));
+        }
+        self
+    }
+
+    /// Makes the VM write its stack and the next instruction to `trace` before
+    /// executing each instruction. Has no effect on the tree-walking backend.
+    pub fn with_trace(mut self, trace: Box<dyn Write>) -> Self {
+        if let Engine::Vm(vm) = self.engine {
+            self.engine = Engine::Vm(vm.with_trace(trace));
+        }
+        self
+    }
+
+    /// Runs a program. The tree-walker returns the value of a trailing expression
+    /// statement; the VM never returns a value.
+    pub fn run(&mut self, bytes: &[u8]) -> RunResult {
+        match parse(bytes) {
+            Ok(statements) => self.execute(statements, bytes, false),
+            Err(errors) => RunResult::StaticErrors(errors),
+        }
+    }
+
+    /// Like [`Lox::run`], but the VM runs the script compiled to `cache` instead if
+    /// it was compiled from `bytes`. The tree-walker ignores the cache.
+    pub fn run_cached(&mut self, bytes: &[u8], cache: &Path) -> RunResult {
+        if let Engine::Vm(vm) = &mut self.engine {
+            if let Ok(script) = loxc::load(cache, bytes) {
+                return match vm.interpret(script) {
+                    Ok(()) => RunResult::Success(None),
+                    Err(e) => RunResult::RuntimeError(e),
+                };
+            }
+        }
+        self.run(bytes)
+    }
+
+    /// Like [`Lox::run`], but also accepts a bare expression without a trailing `;`.
+    /// The VM prints the value of a trailing expression itself instead of returning it.
+    pub fn run_prompt_line(&mut self, bytes: &[u8]) -> RunResult {
+        let (tokens, token_errors) = Scanner::new(bytes.to_vec()).scan_tokens();
+        let (statements, parser_errors) = Parser::new(tokens.clone()).parse();
+        if !token_errors.is_empty() {
+            return RunResult::StaticErrors(static_errors(token_errors, parser_errors));
+        }
+        if parser_errors.is_empty() {
+            return self.execute(statements, bytes, true);
+        }
+        let Ok(expr) = Parser::new(tokens).parse_expression() else {
+            return RunResult::StaticErrors(static_errors(token_errors, parser_errors));
+        };
+        match &self.engine {
+            Engine::TreeWalk(interpreter) => {
+                if let Err(errors) = Resolver::new(interpreter).resolve_expression(&expr) {
+                    return RunResult::StaticErrors(errors.into_iter().map(StaticError::Resolver).collect());
+                }
+                match interpreter.interpret_expression(&expr) {
+                    Ok(value) => RunResult::Success(Some(value)),
+                    Err(e) => RunResult::RuntimeError(e),
+                }
+            },
+            Engine::Vm(_) => {
+                let span = expr.span();
+                self.execute(vec![Stmt::Expression(ExpressionStmt { expression: expr, span })], bytes, true)
+            },
+        }
+    }
+
+    /// Resolves and runs `statements`. With `echo`, the VM prints the value of a
+    /// trailing expression statement, as the REPL shows it.
+    fn execute(&mut self, mut statements: Vec<Stmt>, source: &[u8], echo: bool) -> RunResult {
+        let vm = match &mut self.engine {
+            Engine::TreeWalk(interpreter) => {
+                if let Err(errors) = Resolver::new(interpreter).resolve(&statements) {
+                    return RunResult::StaticErrors(errors.into_iter().map(StaticError::Resolver).collect());
+                }
+                return match interpreter.interpret(&statements) {
+                    Ok(value) => RunResult::Success(value),
+                    Err(e) => RunResult::RuntimeError(e),
+                };
+            },
+            Engine::Vm(vm) => vm,
+        };
+
+        if echo && matches!(statements.last(), Some(Stmt::Expression(_))) {
+            let Some(Stmt::Expression(ExpressionStmt { expression, span })) = statements.pop() else { unreachable!() };
+            statements.push(Stmt::Print(PrintStmt { expression, span }));
+        }
+        let script = match compile_statements(&statements, source) {
+            Ok(script) => script,
+            Err(errors) => return RunResult::StaticErrors(errors),
+        };
+        match vm.interpret(script) {
+            Ok(()) => RunResult::Success(None),
+            Err(e) => RunResult::RuntimeError(e),
+        }
+    }
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-pub fn run_file(path: &Path) -> Result<(), FileError> {
+/// Scanner errors come first: they are usually the cause of any parse errors that follow.
+pub(crate) fn static_errors(token_errors: Vec<TokenError>, parser_errors: Vec<ParserError>) -> Vec<StaticError> {
+    token_errors
+        .into_iter()
+        .map(StaticError::Token)
+        .chain(parser_errors.into_iter().map(StaticError::Parser))
+        .collect()
+}
+
+/// Scans and parses `bytes` without resolving or running them.
+pub fn parse(bytes: &[u8]) -> Result<Vec<Stmt>, Vec<StaticError>> {
+    let (tokens, token_errors) = Scanner::new(bytes.to_vec()).scan_tokens();
+    let (statements, parser_errors) = Parser::new(tokens).parse();
+    if token_errors.is_empty() && parser_errors.is_empty() {
+        Ok(statements)
+    } else {
+        Err(static_errors(token_errors, parser_errors))
+    }
+}
+
+/// Scans, parses, checks and compiles `bytes` to bytecode for the VM backend.
+pub fn compile(bytes: &[u8]) -> Result<Rc<Function>, Vec<StaticError>> {
+    compile_statements(&parse(bytes)?, bytes)
+}
+
+fn compile_statements(statements: &[Stmt], source: &[u8]) -> Result<Rc<Function>, Vec<StaticError>> {
+    if let Err(errors) = Resolver::checker().resolve(statements) {
+        return Err(errors.into_iter().map(StaticError::Resolver).collect());
+    }
+    let source_map = SourceMap::new(source);
+    Compiler::new(&source_map)
+        .compile(statements)
+        .map_err(|errors| errors.into_iter().map(StaticError::Compile).collect())
+}
+
+pub fn run_file(path: &Path) -> Result<RunResult, FileError> {
     let bytes = std::fs::read(path)?;
-    run(&bytes);
-    Ok(())
+    Ok(run(&bytes))
+}
+
+pub fn run(bytes: &[u8]) -> RunResult {
+    Lox::new().run(bytes)
 }
 
-fn run(bytes: &Vec<u8>) {
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Collects everything written to it, for tests that check what a program printed.
+    #[derive(Clone, Default)]
+    pub(crate) struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Output {
+        /// Everything written so far, leaving the output empty.
+        pub(crate) fn take(&self) -> String {
+            String::from_utf8(self.0.take()).unwrap()
+        }
+    }
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run_success() {
+        match run(b"(1 + 2) * 3;") {
+            RunResult::Success(value) => assert_eq!(value, Some(Value::Number(9.0))),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_run_exit_codes() {
+        assert_eq!(run(b"1 + 2;").exit_code(), 0);
+        assert_eq!(run(b"(1 + 2;").exit_code(), 65);
+        assert_eq!(run(b"1 + \"a\";").exit_code(), 70);
+    }
+
+    #[test]
+    fn test_run_keywords() {
+        match run(b"var x = nil or 1; if (x == 1) x = true and \"yes\"; x;") {
+            RunResult::Success(value) => assert_eq!(value, Some(Value::Str("yes".into()))),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_run_reports_all_static_errors() {
+        match run(b"print @1;\nprint (2;\nprint 3; \"open") {
+            RunResult::StaticErrors(errors) => assert!(matches!(
+                errors[..],
+                [
+                    StaticError::Token(TokenError::InvalidToken(1, '@', _)),
+                    StaticError::Token(TokenError::UnterminatedString(3, _)),
+                    StaticError::Parser(ParserError::At { line: 2, .. }),
+                ]
+            )),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_scanner_errors_cause_no_follow_on_parser_errors() {
+        for source in [&b"print \"unterminated"[..], b"print \"\xff\";"] {
+            match run(source) {
+                RunResult::StaticErrors(errors) => assert!(matches!(errors[..], [StaticError::Token(_)]), "{errors:?}"),
+                other => panic!("unexpected result: {other:?}"),
+            }
+            assert!(matches!(compile(source).unwrap_err()[..], [StaticError::Token(_)]));
+        }
+    }
+
+    #[test]
+    fn test_ast_json_round_trip() {
+        fn without_ids(value: &mut serde_json::Value) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    map.remove("id");
+                    map.values_mut().for_each(without_ids);
+                },
+                serde_json::Value::Array(values) => values.iter_mut().for_each(without_ids),
+                _ => {},
+            }
+        }
+
+        let statements = parse(b"class A < B { f(x) { return super.f(x) or this.y; } }\nfor (var i = 0; i < 3; i = i + 1) print -i;").unwrap();
+        let mut json = serde_json::to_value(&statements).unwrap();
+        assert_eq!(json[1]["kind"], "Block");
+        assert_eq!(json[1]["statements"][0]["initializer"]["span"], serde_json::json!({ "start": 67, "end": 68 }));
+        assert_eq!(json[1]["statements"][1]["desugared_for"]["increment"], true);
+
+        let decoded: Vec<Stmt> = serde_json::from_value(json.clone()).unwrap();
+        let mut round_tripped = serde_json::to_value(&decoded).unwrap();
+        without_ids(&mut json);
+        without_ids(&mut round_tripped);
+        assert_eq!(json, round_tripped);
+    }
+
+    #[test]
+    fn test_run_cached_prefers_fresh_bytecode() {
+        // A cache that claims to be compiled from `print 1;` but prints 2 shows
+        // which of the two actually ran.
+        let cache = std::env::temp_dir().join(format!("rinterpreter-{}.loxc", std::process::id()));
+        loxc::write(&cache, &compile(b"print 2;").unwrap(), b"print 1;").unwrap();
+        let output = Output::default();
+        let mut lox = Lox::with_output(Backend::Vm, Box::new(output.clone()));
+        assert_eq!(lox.run_cached(b"print 1;", &cache).exit_code(), 0);
+        assert_eq!(lox.run_cached(b"print 3;", &cache).exit_code(), 0);
+        std::fs::remove_file(&cache).unwrap();
+        assert_eq!(output.take(), "2\n3\n");
+    }
 
+    #[test]
+    fn test_run_prompt_line_accepts_bare_expression() {
+        let mut lox = Lox::new();
+        match lox.run_prompt_line(b"1 + 2") {
+            RunResult::Success(value) => assert_eq!(value, Some(Value::Number(3.0))),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
 }
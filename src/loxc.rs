@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use super::chunk::Function;
+use super::errors::LoxcError;
+
+/// First bytes of every `.loxc` file.
+pub const MAGIC: &[u8; 4] = b"LOXC";
+
+/// Version of the layout below and of the bytecode itself. Bump it whenever an
+/// opcode, [`Function`], [`Chunk`](crate::chunk::Chunk) or `TokenType` changes, so
+/// that older files are recompiled rather than misread.
+pub const FORMAT_VERSION: u16 = 2;
+
+const HEADER_LEN: usize = 18;
+
+/// Serializes a compiled script as a `.loxc` file.
+///
+/// The 18-byte header is the magic number, the big-endian `u16` format version, the
+/// CRC-32 and length of the source the script was compiled from, and the CRC-32 of
+/// the payload, all as big-endian `u32`s. The payload follows: the script,
+/// bincode-encoded with its functions, constants, line tables, error sites and
+/// upvalue descriptors.
+///
+/// The VM trusts the bytecode it runs, so the payload checksum keeps a truncated or
+/// damaged file from reaching it.
+pub fn encode(script: &Function, source: &[u8]) -> Vec<u8> {
+    let payload = bincode::serialize(script).expect("functions serialize to bincode");
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_be_bytes());
+    bytes.extend_from_slice(&crc32fast::hash(source).to_be_bytes());
+    bytes.extend_from_slice(&(source.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&crc32fast::hash(&payload).to_be_bytes());
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+/// Loads the script in `bytes`, provided it was compiled from exactly `source`.
+pub fn decode(bytes: &[u8], source: &[u8]) -> Result<Rc<Function>, LoxcError> {
+    if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+        return Err(LoxcError::BadMagic);
+    }
+    let version = u16::from_be_bytes([bytes[4], bytes[5]]);
+    if version != FORMAT_VERSION {
+        return Err(LoxcError::UnsupportedVersion(version, FORMAT_VERSION));
+    }
+    let checksum = u32::from_be_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]);
+    let len = u32::from_be_bytes([bytes[10], bytes[11], bytes[12], bytes[13]]);
+    if checksum != crc32fast::hash(source) || len as usize != source.len() {
+        return Err(LoxcError::Stale);
+    }
+    let payload_checksum = u32::from_be_bytes([bytes[14], bytes[15], bytes[16], bytes[17]]);
+    let payload = &bytes[HEADER_LEN..];
+    if payload_checksum != crc32fast::hash(payload) {
+        return Err(LoxcError::ChecksumMismatch);
+    }
+    Ok(Rc::new(bincode::deserialize(payload)?))
+}
+
+/// Where the compiled form of `script` is cached: next to it, as `name.loxc`.
+pub fn cache_path(script: &Path) -> PathBuf {
+    script.with_extension("loxc")
+}
+
+pub fn write(path: &Path, script: &Function, source: &[u8]) -> Result<(), LoxcError> {
+    Ok(std::fs::write(path, encode(script, source))?)
+}
+
+/// Loads the script cached at `path` if it exists and is up to date with `source`.
+pub fn load(path: &Path, source: &[u8]) -> Result<Rc<Function>, LoxcError> {
+    decode(&std::fs::read(path)?, source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile;
+
+    #[test]
+    fn test_round_trip() {
+        let source = b"class A { init(x) { this.f = fun_(x); } }\nfun fun_(y) { return -y; }\nprint A(\"s\").f;";
+        let script = compile(source).unwrap();
+        let bytes = encode(&script, source);
+        assert_eq!(&bytes[..6], b"LOXC\0\x02");
+        assert_eq!(decode(&bytes, source).unwrap(), script);
+    }
+
+    #[test]
+    fn test_rejects_stale_and_foreign_files() {
+        let source = b"print 1;";
+        let mut bytes = encode(&compile(source).unwrap(), source);
+        assert!(matches!(decode(&bytes, b"print 2;"), Err(LoxcError::Stale)));
+        assert!(matches!(decode(b"print 1;", source), Err(LoxcError::BadMagic)));
+
+        bytes[5] = 1;
+        assert!(matches!(decode(&bytes, source), Err(LoxcError::UnsupportedVersion(1, FORMAT_VERSION))));
+        bytes[5] = 2;
+        assert!(decode(&bytes, source).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_damaged_payload() {
+        let source = b"fun f(a) { return a + 1; } print f(2);";
+        let bytes = encode(&compile(source).unwrap(), source);
+        assert!(matches!(decode(&bytes[..bytes.len() - 1], source), Err(LoxcError::ChecksumMismatch)));
+        for i in HEADER_LEN..bytes.len() {
+            let mut damaged = bytes.clone();
+            damaged[i] ^= 0x20;
+            assert!(matches!(decode(&damaged, source), Err(LoxcError::ChecksumMismatch)), "byte {i}");
+        }
+    }
+}
@@ -1,3 +1,515 @@
-fn main() {
-    println!("Hello, world!");
+use std::io::IsTerminal;
+use std::path::Path;
+use std::process::ExitCode;
+
+use rinterpreter::codes::ErrorCode;
+use rinterpreter::cst_parser::parse_cst;
+use rinterpreter::diagnostics::{Diagnostic, Renderer};
+use rinterpreter::disassembler::Disassembler;
+use rinterpreter::dot_printer::DotPrinter;
+use rinterpreter::formatter::{format_source, FormatOptions};
+use rinterpreter::rpn_printer::RpnPrinter;
+use rinterpreter::scanner::Scanner;
+use rinterpreter::span::SourceMap;
+use rinterpreter::{compile, loxc, parse, Backend, Lox, RunResult};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+const HISTORY_FILE: &str = ".rinterpreter_history";
+const USAGE: &str = "\
+Usage: rinterpreter [--error-format=human|json] [--backend=tree|vm] [--trace-exec] [--stress-gc] [--log-gc] [script]
+       rinterpreter [--error-format=human|json] (--dump-tokens | --dump-ast=json|rpn|dot | --dump-cst | --dump-bytecode) <script>
+       rinterpreter --explain <code>
+       rinterpreter fmt [--check] [--indent=<n>] [--line-width=<n>] <file>...
+       rinterpreter compile <script>...";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorFormat {
+    Human,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AstFormat {
+    Json,
+    /// Reverse Polish notation, one statement per line.
+    Rpn,
+    /// A Graphviz digraph.
+    Dot,
+}
+
+#[derive(Debug)]
+struct Options {
+    script: Option<String>,
+    error_format: ErrorFormat,
+    backend: Backend,
+    explain: Option<String>,
+    dump_tokens: bool,
+    dump_ast: Option<AstFormat>,
+    dump_cst: bool,
+    dump_bytecode: bool,
+    trace_exec: bool,
+    stress_gc: bool,
+    log_gc: bool,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            script: None,
+            error_format: ErrorFormat::Human,
+            backend: Backend::TreeWalk,
+            explain: None,
+            dump_tokens: false,
+            dump_ast: None,
+            dump_cst: false,
+            dump_bytecode: false,
+            trace_exec: false,
+            stress_gc: false,
+            log_gc: false,
+        };
+        while let Some(arg) = args.next() {
+            if arg == "--explain" {
+                options.explain = Some(args.next().ok_or("Expected an error code after '--explain'.")?);
+            } else if let Some(code) = arg.strip_prefix("--explain=") {
+                options.explain = Some(code.to_string());
+            } else if arg == "--dump-tokens" {
+                options.dump_tokens = true;
+            } else if arg == "--dump-cst" {
+                options.dump_cst = true;
+            } else if arg == "--dump-bytecode" {
+                options.dump_bytecode = true;
+            } else if arg == "--trace-exec" {
+                options.trace_exec = true;
+            } else if arg == "--stress-gc" {
+                options.stress_gc = true;
+            } else if arg == "--log-gc" {
+                options.log_gc = true;
+            } else if let Some(format) = arg.strip_prefix("--dump-ast=") {
+                options.dump_ast = match format {
+                    "json" => Some(AstFormat::Json),
+                    "rpn" => Some(AstFormat::Rpn),
+                    "dot" => Some(AstFormat::Dot),
+                    _ => return Err(format!("Unknown AST format '{format}'.")),
+                };
+            } else if let Some(format) = arg.strip_prefix("--error-format=") {
+                options.error_format = match format {
+                    "human" => ErrorFormat::Human,
+                    "json" => ErrorFormat::Json,
+                    _ => return Err(format!("Unknown error format '{format}'.")),
+                };
+            } else if let Some(backend) = arg.strip_prefix("--backend=") {
+                options.backend = match backend {
+                    "tree" => Backend::TreeWalk,
+                    "vm" => Backend::Vm,
+                    _ => return Err(format!("Unknown backend '{backend}'.")),
+                };
+            } else if arg.starts_with("--") {
+                return Err(format!("Unknown option '{arg}'."));
+            } else if options.script.is_none() {
+                options.script = Some(arg);
+            } else {
+                return Err("Expected at most one script.".to_string());
+            }
+        }
+        if (options.dump_tokens || options.dump_ast.is_some() || options.dump_cst || options.dump_bytecode) && options.script.is_none() {
+            return Err("Dumping tokens, a syntax tree or bytecode needs a script.".to_string());
+        }
+        if (options.trace_exec || options.stress_gc || options.log_gc) && options.backend != Backend::Vm {
+            return Err("'--trace-exec', '--stress-gc' and '--log-gc' need '--backend=vm'.".to_string());
+        }
+        Ok(options)
+    }
+}
+
+#[derive(Debug)]
+struct FmtOptions {
+    files: Vec<String>,
+    check: bool,
+    format: FormatOptions,
+}
+
+impl FmtOptions {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = FmtOptions {
+            files: Vec::new(),
+            check: false,
+            format: FormatOptions::default(),
+        };
+        for arg in args {
+            if arg == "--check" {
+                options.check = true;
+            } else if let Some(indent) = arg.strip_prefix("--indent=") {
+                options.format.indent = indent.parse().map_err(|_| format!("Invalid indent '{indent}'."))?;
+            } else if let Some(width) = arg.strip_prefix("--line-width=") {
+                options.format.line_width = width.parse().map_err(|_| format!("Invalid line width '{width}'."))?;
+            } else if arg.starts_with("--") {
+                return Err(format!("Unknown option '{arg}'."));
+            } else {
+                options.files.push(arg);
+            }
+        }
+        if options.files.is_empty() {
+            return Err("Expected at least one file to format.".to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "fmt") {
+        args.next();
+        return match FmtOptions::parse(args) {
+            Ok(options) => run_fmt(&options),
+            Err(e) => {
+                eprintln!("{e}\n{USAGE}");
+                ExitCode::from(64)
+            }
+        };
+    }
+    if args.peek().is_some_and(|arg| arg == "compile") {
+        args.next();
+        let scripts: Vec<String> = args.collect();
+        if scripts.is_empty() || scripts.iter().any(|script| script.starts_with("--")) {
+            eprintln!("Expected the scripts to compile.\n{USAGE}");
+            return ExitCode::from(64);
+        }
+        return run_compile(&scripts);
+    }
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            return ExitCode::from(64);
+        }
+    };
+    if let Some(code) = &options.explain {
+        return explain(code);
+    }
+    match &options.script {
+        Some(path) => run_script(Path::new(path), &options),
+        None => run_prompt(&options),
+    }
+}
+
+/// Rewrites each file in canonical form or, with `--check`, lists the files that
+/// would change and fails if there are any.
+fn run_fmt(options: &FmtOptions) -> ExitCode {
+    let mut exit_code = ExitCode::SUCCESS;
+    for file in &options.files {
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Failed to read {file}: {e}");
+                return ExitCode::from(66);
+            }
+        };
+        let formatted = match format_source(&source, options.format) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                report(&RunResult::StaticErrors(errors), source.as_bytes(), file, ErrorFormat::Human);
+                exit_code = ExitCode::from(65);
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if options.check {
+            println!("Would reformat {file}");
+            if exit_code == ExitCode::SUCCESS {
+                exit_code = ExitCode::from(1);
+            }
+        } else if let Err(e) = std::fs::write(file, formatted) {
+            eprintln!("Failed to write {file}: {e}");
+            return ExitCode::from(74);
+        }
+    }
+    exit_code
+}
+
+/// Compiles each script to bytecode and saves it next to the script as `.loxc`,
+/// where `--backend=vm` picks it up while it matches the source.
+fn run_compile(scripts: &[String]) -> ExitCode {
+    let mut exit_code = ExitCode::SUCCESS;
+    for script in scripts {
+        let bytes = match std::fs::read(script) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("Failed to read {script}: {e}");
+                return ExitCode::from(66);
+            }
+        };
+        let compiled = match compile(&bytes) {
+            Ok(compiled) => compiled,
+            Err(errors) => {
+                report(&RunResult::StaticErrors(errors), &bytes, script, ErrorFormat::Human);
+                exit_code = ExitCode::from(65);
+                continue;
+            }
+        };
+        let path = loxc::cache_path(Path::new(script));
+        if let Err(e) = loxc::write(&path, &compiled, &bytes) {
+            eprintln!("Failed to write {}: {e}", path.display());
+            return ExitCode::from(74);
+        }
+    }
+    exit_code
+}
+
+fn explain(code: &str) -> ExitCode {
+    match ErrorCode::parse(code) {
+        Some(code) => {
+            println!("{code}: {}\n\n{}", code.title(), code.explanation());
+            ExitCode::SUCCESS
+        },
+        None => {
+            eprintln!("'{code}' is not a known error code.");
+            ExitCode::from(64)
+        }
+    }
+}
+
+fn run_script(path: &Path, options: &Options) -> ExitCode {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to read file: {e}");
+            return ExitCode::from(66);
+        }
+    };
+    let file = path.display().to_string();
+    if options.dump_tokens {
+        return dump_tokens(&bytes, &file, options);
+    }
+    if let Some(format) = options.dump_ast {
+        return dump_ast(&bytes, &file, format, options);
+    }
+    if options.dump_cst {
+        return dump_cst(&bytes, &file, options);
+    }
+    if options.dump_bytecode {
+        return dump_bytecode(&bytes, &file, options);
+    }
+    let result = lox(options).run_cached(&bytes, &loxc::cache_path(path));
+    report(&result, &bytes, &file, options.error_format);
+    ExitCode::from(result.exit_code() as u8)
+}
+
+/// Prints every token, including error tokens, as a JSON array; scanner errors are
+/// reported as usual.
+fn dump_tokens(bytes: &[u8], file: &str, options: &Options) -> ExitCode {
+    let (tokens, errors) = Scanner::new(bytes.to_vec()).scan_tokens();
+    println!("{}", serde_json::to_string_pretty(&tokens).expect("tokens serialize to JSON"));
+    let result = if errors.is_empty() {
+        RunResult::Success(None)
+    } else {
+        RunResult::StaticErrors(errors.into_iter().map(Into::into).collect())
+    };
+    report(&result, bytes, file, options.error_format);
+    ExitCode::from(result.exit_code() as u8)
+}
+
+/// Prints the concrete syntax tree, which exists even for malformed input, then
+/// reports the errors that put tokens in its `Error` nodes.
+fn dump_cst(bytes: &[u8], file: &str, options: &Options) -> ExitCode {
+    print!("{:?}", parse_cst(bytes));
+    let result = match parse(bytes) {
+        Ok(_) => RunResult::Success(None),
+        Err(errors) => RunResult::StaticErrors(errors),
+    };
+    report(&result, bytes, file, options.error_format);
+    ExitCode::from(result.exit_code() as u8)
+}
+
+fn dump_ast(bytes: &[u8], file: &str, format: AstFormat, options: &Options) -> ExitCode {
+    match parse(bytes) {
+        Ok(statements) => {
+            match format {
+                AstFormat::Json => println!("{}", serde_json::to_string_pretty(&statements).expect("AST serializes to JSON")),
+                AstFormat::Rpn => print!("{}", RpnPrinter.print_program(&statements).expect("printing the AST cannot fail")),
+                AstFormat::Dot => print!("{}", DotPrinter::new().print_program(&statements).expect("printing the AST cannot fail")),
+            }
+            ExitCode::SUCCESS
+        },
+        Err(errors) => {
+            let result = RunResult::StaticErrors(errors);
+            report(&result, bytes, file, options.error_format);
+            ExitCode::from(result.exit_code() as u8)
+        }
+    }
+}
+
+/// Disassembles every function of the compiled script.
+fn dump_bytecode(bytes: &[u8], file: &str, options: &Options) -> ExitCode {
+    match compile(bytes) {
+        Ok(script) => {
+            let source_map = SourceMap::new(bytes);
+            print!("{}", Disassembler::new().with_source(&source_map).function(&script));
+            ExitCode::SUCCESS
+        },
+        Err(errors) => {
+            let result = RunResult::StaticErrors(errors);
+            report(&result, bytes, file, options.error_format);
+            ExitCode::from(result.exit_code() as u8)
+        }
+    }
+}
+
+fn lox(options: &Options) -> Lox {
+    let mut lox = Lox::with_backend(options.backend);
+    if options.trace_exec {
+        lox = lox.with_trace(Box::new(std::io::stderr()));
+    }
+    if options.stress_gc {
+        lox = lox.with_stress_gc();
+    }
+    if options.log_gc {
+        lox = lox.with_gc_log(Box::new(std::io::stderr()));
+    }
+    lox
+}
+
+fn run_prompt(options: &Options) -> ExitCode {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Failed to start REPL: {e}");
+            return ExitCode::from(74);
+        }
+    };
+    let _ = editor.load_history(HISTORY_FILE);
+
+    let mut lox = lox(options);
+    let mut buffer = String::new();
+    loop {
+        let prompt = if buffer.is_empty() { "> " } else { ". " };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                buffer.push_str(&line);
+                buffer.push('\n');
+                if !is_balanced(&buffer) {
+                    continue;
+                }
+                let source = std::mem::take(&mut buffer);
+                if source.trim().is_empty() {
+                    continue;
+                }
+                let _ = editor.add_history_entry(source.trim_end());
+                match lox.run_prompt_line(source.as_bytes()) {
+                    RunResult::Success(Some(value)) => println!("{value}"),
+                    result => report(&result, source.as_bytes(), "<repl>", options.error_format),
+                }
+            },
+            Err(ReadlineError::Interrupted) => buffer.clear(),
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("{e}");
+                break;
+            }
+        }
+    }
+
+    let _ = editor.save_history(HISTORY_FILE);
+    ExitCode::SUCCESS
+}
+
+fn report(result: &RunResult, source: &[u8], file: &str, format: ErrorFormat) {
+    let diagnostics: Vec<Diagnostic> = match result {
+        RunResult::Success(_) => return,
+        RunResult::StaticErrors(errors) => errors.iter().map(Diagnostic::from).collect(),
+        RunResult::RuntimeError(e) => vec![e.into()],
+    };
+    let source_map = SourceMap::new(source);
+    match format {
+        ErrorFormat::Human => {
+            let renderer = Renderer::new(&source_map).with_file(file).with_color(use_color());
+            for diagnostic in &diagnostics {
+                eprintln!("{}", renderer.render(diagnostic));
+            }
+            if let Some(code) = diagnostics.iter().find_map(|diagnostic| diagnostic.code) {
+                eprintln!("For more information about an error, try `rinterpreter --explain {code}`.");
+            }
+        },
+        ErrorFormat::Json => {
+            for diagnostic in &diagnostics {
+                let json = diagnostic.to_json(&source_map, Some(file));
+                eprintln!("{}", serde_json::to_string(&json).expect("diagnostics serialize to JSON"));
+            }
+        },
+    }
+}
+
+/// Colour diagnostics only for an interactive terminal, honouring `NO_COLOR`.
+fn use_color() -> bool {
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// Whether every `(` and `{` in the input has been closed, ignoring those inside
+/// strings and comments. Used to decide if the REPL should keep reading lines.
+fn is_balanced(source: &str) -> bool {
+    let mut depth: i64 = 0;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            '"' if !chars.by_ref().any(|c| c == '"') => return false,
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = '\0';
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        closed = true;
+                        break;
+                    }
+                    previous = c;
+                }
+                if !closed {
+                    return false;
+                }
+            },
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_balanced_nested() {
+        assert!(is_balanced("fun f(a) { if (a) { print (a); } }"));
+        assert!(!is_balanced("fun f(a) { if (a) {"));
+        assert!(!is_balanced("print (1 + (2"));
+        assert!(is_balanced("{\n  print 1;\n}\n"));
+    }
+
+    #[test]
+    fn test_is_balanced_ignores_strings_and_comments() {
+        assert!(is_balanced("print \"(\";"));
+        assert!(is_balanced("print 1; // {"));
+        assert!(is_balanced("/* ( { */ print 1;"));
+        assert!(!is_balanced("{ print \"}\";"));
+        assert!(!is_balanced("print \"unterminated"));
+        assert!(!is_balanced("/* still open"));
+    }
+
+    #[test]
+    fn test_is_balanced_stops_on_unbalanced_closers() {
+        // Extra closers can't be fixed by reading more lines, so the parser reports them.
+        assert!(is_balanced(")"));
+        assert!(is_balanced("print 1; }"));
+    }
 }
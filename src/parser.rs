@@ -1,28 +1,364 @@
-use std::fmt::Binary;
+use std::collections::HashSet;
 
+use crate::codes::ErrorCode;
+use crate::span::Span;
 use crate::token::Literal;
 use crate::token_type::TokenType;
-use crate::{BinaryExpr, GroupingExpr, LiteralExpr, ParserError, UnaryExpr};
+use crate::{AssignExpr, BinaryExpr, BlockStmt, CallExpr, ClassStmt, ExpressionStmt, ForClauses, FunctionStmt, GetExpr, GroupingExpr, IfStmt, LiteralExpr, LogicalExpr, ParserError, PrintStmt, ReturnStmt, SetExpr, Stmt, SuperExpr, ThisExpr, UnaryExpr, VarStmt, VariableExpr, WhileStmt};
 
 use super::token::Token;
 use super::ast::Expr;
 
+const MAX_ARGUMENTS: usize = 255;
+
 #[derive(Debug)]
 pub struct Parser {
     pub tokens: Vec<Token>,
     pub current: u32,
+    errors: Vec<ParserError>,
+    /// Start offsets of the tokens that directly follow a skipped error token.
+    after_error: HashSet<usize>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
+        // The scanner has already reported its error tokens; skipping them lets
+        // parsing carry on and find later problems. Trivia from a lossless scan is
+        // dropped too.
+        let mut kept = Vec::new();
+        let mut after_error = HashSet::new();
+        let mut follows_error = false;
+        for token in tokens {
+            if token.token_type == TokenType::Error {
+                follows_error = true;
+            } else if !token.token_type.is_trivia() {
+                if std::mem::take(&mut follows_error) {
+                    after_error.insert(token.span.start);
+                }
+                kept.push(token);
+            }
+        }
         Self {
-            tokens,
+            tokens: kept,
             current: 0,
+            errors: Vec::new(),
+            after_error,
+        }
+    }
+
+    /// Parses every declaration in the input. After a syntax error the parser skips
+    /// to the next statement boundary and carries on, so the statements that did
+    /// parse are returned alongside every error found.
+    pub fn parse(&mut self) -> (Vec<Stmt>, Vec<ParserError>) {
+        let mut statements = Vec::new();
+        loop {
+            if self.is_at_end() {
+                break;
+            }
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
+        }
+
+        (statements, std::mem::take(&mut self.errors))
+    }
+
+    /// Parses the whole input as a single expression, as typed at the REPL without a
+    /// trailing `;`.
+    pub fn parse_expression(&mut self) -> Result<Expr, ParserError> {
+        let expr = self.expression()?;
+        if !self.is_at_end() {
+            return Err(self.error_at_current(ErrorCode::ExpectedEndOfExpression, "Expect end of expression.", None));
+        }
+        match self.errors.drain(..).next() {
+            Some(error) => Err(error),
+            None => Ok(expr),
+        }
+    }
+
+    fn declaration(&mut self) -> Option<Stmt> {
+        let result = if self.match_types(vec![TokenType::Class]) {
+            self.class_declaration()
+        } else if self.match_types(vec![TokenType::Fun]) {
+            self.function("function").map(Stmt::Function)
+        } else if self.match_types(vec![TokenType::Var]) {
+            self.var_declaration()
+        } else {
+            self.statement()
+        };
+
+        match result {
+            Ok(stmt) => Some(stmt),
+            Err(e) => {
+                self.report(e);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().span;
+        let name = self.consume(TokenType::Identifier, "Expect class name.")?;
+
+        let superclass = if self.match_types(vec![TokenType::Less]) {
+            let superclass_name = self.consume(TokenType::Identifier, "Expect superclass name.")?;
+            Some(VariableExpr::new(superclass_name))
+        } else {
+            None
+        };
+
+        let left_brace = self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        loop {
+            if self.check(TokenType::RightBrace) || self.is_at_end() {
+                break;
+            }
+            methods.push(self.function("method")?);
+        }
+
+        self.consume_closing(TokenType::RightBrace, "Expect '}' after class body.", left_brace.span)?;
+        Ok(Stmt::Class(ClassStmt::new(name, superclass, methods, self.span_from(keyword))))
+    }
+
+    fn function(&mut self, kind: &str) -> Result<FunctionStmt, ParserError> {
+        // Functions start at `fun`, methods at their name.
+        let start = if self.previous().token_type == TokenType::Fun { self.previous().span } else { self.peek().span };
+        let name = self.consume(TokenType::Identifier, &format!("Expect {kind} name."))?;
+        let left_paren = self.consume(TokenType::LeftParen, &format!("Expect '(' after {kind} name."))?;
+
+        let mut params = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    let error = self.error_at_current(ErrorCode::TooManyArguments, "Can't have more than 255 parameters.", None);
+                    self.report(error);
+                }
+                params.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
+                if !self.match_types(vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume_closing(TokenType::RightParen, "Expect ')' after parameters.", left_paren.span)?;
+
+        self.consume(TokenType::LeftBrace, &format!("Expect '{{' before {kind} body."))?;
+        let body = self.block()?;
+        Ok(FunctionStmt::new(name, params, body, self.span_from(start)))
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().span;
+        let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
+
+        let initializer = if self.match_types(vec![TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after variable declaration.")?;
+        Ok(Stmt::Var(VarStmt::new(name, initializer, self.span_from(keyword))))
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParserError> {
+        if self.match_types(vec![TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.match_types(vec![TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.match_types(vec![TokenType::Print]) {
+            return self.print_statement();
+        }
+        if self.match_types(vec![TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.match_types(vec![TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.match_types(vec![TokenType::LeftBrace]) {
+            let left_brace = self.previous().span;
+            let statements = self.block()?;
+            return Ok(Stmt::Block(BlockStmt::new(statements, self.span_from(left_brace))));
+        }
+        self.expression_statement()
+    }
+
+    fn for_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().span;
+        let left_paren = self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.match_types(vec![TokenType::Semicolon]) {
+            None
+        } else if self.match_types(vec![TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        let condition_end = self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let increment = if self.check(TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume_closing(TokenType::RightParen, "Expect ')' after for clauses.", left_paren.span)?;
+
+        let clauses = ForClauses {
+            initializer: initializer.is_some(),
+            condition: condition.is_some(),
+            increment: increment.is_some(),
+        };
+        let mut body = self.statement()?;
+        let span = self.span_from(keyword);
+
+        if let Some(increment) = increment {
+            let body_span = body.span().merge(increment.span());
+            let increment_span = increment.span();
+            body = Stmt::Block(BlockStmt::new(
+                vec![body, Stmt::Expression(ExpressionStmt::new(increment, increment_span))],
+                body_span,
+            ));
+        }
+
+        let condition = condition.unwrap_or_else(|| Expr::Literal(LiteralExpr::new(Some(Literal::True), condition_end.span)));
+        body = Stmt::While(WhileStmt::new(condition, body, span).desugared_from(clauses));
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(BlockStmt::new(vec![initializer, body], span));
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().span;
+        let left_paren = self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume_closing(TokenType::RightParen, "Expect ')' after if condition.", left_paren.span)?;
+
+        let then_branch = self.statement()?;
+        let else_branch = if self.match_types(vec![TokenType::Else]) {
+            Some(self.statement()?)
+        } else {
+            None
+        };
+
+        Ok(Stmt::If(IfStmt::new(condition, then_branch, else_branch, self.span_from(keyword))))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().span;
+        let left_paren = self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume_closing(TokenType::RightParen, "Expect ')' after condition.", left_paren.span)?;
+        let body = self.statement()?;
+
+        Ok(Stmt::While(WhileStmt::new(condition, body, self.span_from(keyword))))
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().span;
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(Stmt::Print(PrintStmt::new(value, self.span_from(keyword))))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous().clone();
+        let value = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+        let span = self.span_from(keyword.span);
+        Ok(Stmt::Return(ReturnStmt::new(keyword, value, span)))
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParserError> {
+        let expr = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        let span = self.span_from(expr.span());
+        Ok(Stmt::Expression(ExpressionStmt::new(expr, span)))
+    }
+
+    /// Parses the rest of a block whose `{` has just been consumed.
+    fn block(&mut self) -> Result<Vec<Stmt>, ParserError> {
+        let left_brace = self.previous().span;
+        let mut statements = Vec::new();
+
+        loop {
+            if self.check(TokenType::RightBrace) || self.is_at_end() {
+                break;
+            }
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
         }
+
+        self.consume_closing(TokenType::RightBrace, "Expect '}' after block.", left_brace)?;
+        Ok(statements)
     }
 
     fn expression(&mut self) -> Result<Expr, ParserError> {
-        self.equality()
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<Expr, ParserError> {
+        let expr = self.or()?;
+
+        if self.match_types(vec![TokenType::Equal]) {
+            let equals = self.previous().clone();
+            let value = self.assignment()?;
+
+            match expr {
+                Expr::Variable(variable) => return Ok(Expr::Assign(AssignExpr::new(variable.name, value))),
+                Expr::Get(get) => return Ok(Expr::Set(SetExpr::new(*get.object, get.name, value))),
+                _ => {}
+            }
+            // Nothing is out of step here, so report the error and keep parsing.
+            self.report(ParserError::InvalidAssignmentTarget(equals.line, equals.lexeme.to_string(), equals.span));
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.and()?;
+
+        loop {
+            if !self.match_types(vec![TokenType::Or]) {
+                break;
+            }
+            let operator = self.previous().clone();
+            let right = self.and()?;
+            expr = Expr::Logical(LogicalExpr::new(expr, operator, right));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.equality()?;
+
+        loop {
+            if !self.match_types(vec![TokenType::And]) {
+                break;
+            }
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+            expr = Expr::Logical(LogicalExpr::new(expr, operator, right));
+        }
+        Ok(expr)
     }
 
     fn equality(&mut self) -> Result<Expr, ParserError> {
@@ -51,16 +387,49 @@ impl Parser {
         false
     }
 
-    fn consume(&mut self, token_type: TokenType) -> Result<(), ParserError> {
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, ParserError> {
+        if self.check(token_type.clone()) {
+            self.advance();
+            return Ok(self.previous().clone())
+        }
+        Err(self.error_at_current(expected_code(&token_type), message, None))
+    }
+
+    /// Like `consume`, for a closing delimiter whose opening one is at `opening`.
+    fn consume_closing(&mut self, token_type: TokenType, message: &str, opening: Span) -> Result<Token, ParserError> {
         if self.check(token_type.clone()) {
             self.advance();
-            return Ok(())
+            return Ok(self.previous().clone())
         }
-        let token = self.peek().clone();
-        if token_type == TokenType::Eof {
-            Err(ParserError::Eof(token.line))
+        Err(self.error_at_current(expected_code(&token_type), message, Some(opening)))
+    }
+
+    /// Span from `start` to the end of the token just consumed.
+    fn span_from(&self, start: Span) -> Span {
+        start.merge(self.previous().span)
+    }
+
+    /// Records `error`, unless it is at the token right after one the scanner
+    /// rejected: then it only reports that the bad token is missing.
+    fn report(&mut self, error: ParserError) {
+        if !self.after_error.contains(&error.span().start) {
+            self.errors.push(error);
+        }
+    }
+
+    fn error_at_current(&self, code: ErrorCode, message: &str, opening: Option<Span>) -> ParserError {
+        let token = self.peek();
+        if token.token_type == TokenType::Eof {
+            ParserError::Eof { line: token.line, message: message.to_string(), span: token.span, related: opening, code }
         } else {
-            Err(ParserError::At(token.line, token.lexeme))
+            ParserError::At {
+                line: token.line,
+                lexeme: token.lexeme.to_string(),
+                message: message.to_string(),
+                span: token.span,
+                related: opening,
+                code,
+            }
         }
     }
 
@@ -68,7 +437,7 @@ impl Parser {
         if self.is_at_end() {
             return false;
         }
-        return self.peek().token_type == token_type
+        self.peek().token_type == token_type
     }
 
     fn advance(&mut self) {
@@ -79,7 +448,7 @@ impl Parser {
     }
  
     fn is_at_end(&self) -> bool {
-        return self.peek().token_type == TokenType::Eof
+        self.peek().token_type == TokenType::Eof
     }
 
     fn peek(&self) -> &Token {
@@ -142,32 +511,84 @@ impl Parser {
             let unary_expr = UnaryExpr::new(operator.clone(), right);
             return Ok(Expr::Unary(unary_expr));
         }
-        Ok(self.primary()?.unwrap())
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr, ParserError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_types(vec![TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_types(vec![TokenType::Dot]) {
+                let name = self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
+                expr = Expr::Get(GetExpr::new(expr, name));
+            } else {
+                break;
+            }
+        }
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParserError> {
+        let left_paren = self.previous().span;
+        let mut arguments = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    let error = self.error_at_current(ErrorCode::TooManyArguments, "Can't have more than 255 arguments.", None);
+                    self.report(error);
+                }
+                arguments.push(self.expression()?);
+                if !self.match_types(vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume_closing(TokenType::RightParen, "Expect ')' after arguments.", left_paren)?;
+        Ok(Expr::Call(CallExpr::new(callee, paren, arguments)))
     }
 
-    fn primary(&mut self) -> Result<Option<Expr>, ParserError> {
+    fn primary(&mut self) -> Result<Expr, ParserError> {
         if self.match_types(vec![TokenType::False]) {
-            return Ok(Some(Expr::Literal(LiteralExpr::new(Some(Literal::False)))));
+            return Ok(Expr::Literal(LiteralExpr::new(Some(Literal::False), self.previous().span)));
         }
         if self.match_types(vec![TokenType::True]) {
-            return Ok(Some(Expr::Literal(LiteralExpr::new(Some(Literal::True)))));
+            return Ok(Expr::Literal(LiteralExpr::new(Some(Literal::True), self.previous().span)));
         }
         if self.match_types(vec![TokenType::Nil]) {
-            return Ok(Some(Expr::Literal(LiteralExpr::new(None))));
+            return Ok(Expr::Literal(LiteralExpr::new(None, self.previous().span)));
         }
 
         if self.match_types(vec![TokenType::Number, TokenType::String]) {
-            return Ok(Some(Expr::Literal(LiteralExpr::new(self.previous().litteral.clone()))));
+            return Ok(Expr::Literal(LiteralExpr::new(self.previous().litteral.clone(), self.previous().span)));
+        }
+
+        if self.match_types(vec![TokenType::Super]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self.consume(TokenType::Identifier, "Expect superclass method name.")?;
+            return Ok(Expr::Super(SuperExpr::new(keyword, method)));
+        }
+
+        if self.match_types(vec![TokenType::This]) {
+            return Ok(Expr::This(ThisExpr::new(self.previous().clone())));
+        }
+
+        if self.match_types(vec![TokenType::Identifier]) {
+            return Ok(Expr::Variable(VariableExpr::new(self.previous().clone())));
         }
 
         if self.match_types(vec![TokenType::LeftParen]) {
+            let left_paren = self.previous().span;
             let expr = self.expression()?;
-            self.consume(TokenType::RightParen)?; 
-            let grouping_expr = GroupingExpr::new(expr);
-            return Ok(Some(Expr::Grouping(grouping_expr)));
+            let right_paren = self.consume_closing(TokenType::RightParen, "Expect ')' after expression.", left_paren)?;
+            let grouping_expr = GroupingExpr::new(expr, left_paren.merge(right_paren.span));
+            return Ok(Expr::Grouping(grouping_expr));
         }
 
-        Ok(None)
+        Err(self.error_at_current(ErrorCode::ExpectedExpression, "Expect expression.", None))
     }
 
     fn synchronize(&mut self) {
@@ -193,4 +614,150 @@ impl Parser {
     }
 }
 
+/// The code reported when `token_type` was required but something else was found.
+fn expected_code(token_type: &TokenType) -> ErrorCode {
+    match token_type {
+        TokenType::RightParen => ErrorCode::ExpectedRightParen,
+        TokenType::RightBrace => ErrorCode::ExpectedRightBrace,
+        TokenType::Semicolon => ErrorCode::ExpectedSemicolon,
+        TokenType::LeftParen => ErrorCode::ExpectedLeftParen,
+        TokenType::LeftBrace => ErrorCode::ExpectedLeftBrace,
+        TokenType::Dot => ErrorCode::ExpectedDot,
+        _ => ErrorCode::ExpectedIdentifier,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        let (tokens, errors) = crate::scanner::Scanner::new(source.as_bytes().to_vec()).scan_tokens();
+        assert!(errors.is_empty(), "{errors:?}");
+        tokens
+    }
+
+    #[test]
+    fn test_var_declaration() {
+        let mut parser = Parser::new(tokens("var a = 1;"));
+        let (statements, errors) = parser.parse();
+        assert!(errors.is_empty(), "{errors:?}");
+        match &statements[..] {
+            [Stmt::Var(stmt)] => {
+                assert_eq!(stmt.name.lexeme, "a");
+                assert!(stmt.initializer.is_some());
+            },
+            other => panic!("unexpected statements: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_block() {
+        let mut parser = Parser::new(tokens("{ print a; }"));
+        let (statements, errors) = parser.parse();
+        assert!(errors.is_empty(), "{errors:?}");
+        match &statements[..] {
+            [Stmt::Block(block)] => assert!(matches!(block.statements[..], [Stmt::Print(_)])),
+            other => panic!("unexpected statements: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_for_desugars_to_while() {
+        let mut parser = Parser::new(tokens("for (var i = 0; i < 3; i = i + 1) print i;"));
+        let (statements, errors) = parser.parse();
+        assert!(errors.is_empty(), "{errors:?}");
+        let [Stmt::Block(outer)] = &statements[..] else {
+            panic!("unexpected statements: {statements:?}");
+        };
+        let [Stmt::Var(_), Stmt::While(while_stmt)] = &outer.statements[..] else {
+            panic!("unexpected block: {outer:?}");
+        };
+        let Stmt::Block(body) = while_stmt.body.as_ref() else {
+            panic!("unexpected body: {:?}", while_stmt.body);
+        };
+        assert!(matches!(body.statements[..], [Stmt::Print(_), Stmt::Expression(_)]));
+        assert_eq!(while_stmt.desugared_for, Some(ForClauses { initializer: true, condition: true, increment: true }));
+    }
+
+    #[test]
+    fn test_for_without_clauses() {
+        let mut parser = Parser::new(tokens("for (;;) print i;"));
+        let (statements, errors) = parser.parse();
+        assert!(errors.is_empty(), "{errors:?}");
+        let [Stmt::While(while_stmt)] = &statements[..] else {
+            panic!("unexpected statements: {statements:?}");
+        };
+        assert!(matches!(&while_stmt.condition, Expr::Literal(literal) if literal.value == Some(Literal::True)));
+        assert!(matches!(while_stmt.body.as_ref(), Stmt::Print(_)));
+        assert_eq!(while_stmt.desugared_for, Some(ForClauses { initializer: false, condition: false, increment: false }));
+    }
 
+    #[test]
+    fn test_logical_precedence() {
+        let mut parser = Parser::new(tokens("a or b and c"));
+        let expr = parser.parse_expression().unwrap();
+        assert_eq!(crate::ast_printer::AstPrinter.print(expr).unwrap(), "(or a (and b c))");
+    }
+
+    #[test]
+    fn test_expression_spans() {
+        let expr = Parser::new(tokens("  -(1 + 2) * foo.bar(3)")).parse_expression().unwrap();
+        assert_eq!(expr.span(), Span::new(2, 23));
+
+        let Expr::Binary(binary) = expr else {
+            panic!("unexpected expression: {expr:?}");
+        };
+        let Expr::Unary(unary) = binary.left.as_ref() else {
+            panic!("unexpected operand: {:?}", binary.left);
+        };
+        assert_eq!(unary.right.span(), Span::new(3, 10));
+        assert_eq!(binary.right.span(), Span::new(13, 23));
+    }
+
+    #[test]
+    fn test_invalid_assignment_target() {
+        let mut parser = Parser::new(tokens("1 = 2;"));
+        let (_, errors) = parser.parse();
+        assert!(matches!(errors[..], [ParserError::InvalidAssignmentTarget(1, _, _)]));
+    }
+
+    #[test]
+    fn test_invalid_assignment_target_keeps_parsing() {
+        let (statements, errors) = Parser::new(tokens("f(1 = 2, 3 +);\nprint 3;")).parse();
+        assert!(matches!(errors[..], [ParserError::InvalidAssignmentTarget(1, _, _), ParserError::At { line: 1, .. }]), "{errors:?}");
+        assert!(matches!(statements[..], [Stmt::Print(_)]));
+
+        let mut parser = Parser::new(tokens("a + b = c"));
+        assert!(matches!(parser.parse_expression(), Err(ParserError::InvalidAssignmentTarget(1, _, _))));
+    }
+
+    #[test]
+    fn test_reports_error_per_statement() {
+        let mut parser = Parser::new(tokens("var 1; print 2"));
+        let (_, errors) = parser.parse();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].to_string(), "[line 1] Error at '1': Expect variable name.");
+        assert_eq!(errors[1].to_string(), "[line 1] Error at end: Expect ';' after value.");
+    }
+
+    #[test]
+    fn test_missing_expression() {
+        for source in ["+", ")", "print;"] {
+            let (_, errors) = Parser::new(tokens(source)).parse();
+            assert!(matches!(&errors[..], [ParserError::At { line: 1, message, .. }] if message == "Expect expression."), "{source}: {errors:?}");
+        }
+    }
+
+    #[test]
+    fn test_recovers_with_partial_ast() {
+        let source = "print 1;\nvar = 2;\nprint 3;\nfun f() { return * ; }\nprint (4;\nprint 5;";
+        let (statements, errors) = Parser::new(tokens(source)).parse();
+        let lines: Vec<u64> = errors.iter().map(|e| match e {
+            ParserError::At { line, .. } | ParserError::Eof { line, .. } => *line,
+            other => panic!("unexpected error: {other:?}"),
+        }).collect();
+        assert_eq!(lines, vec![2, 4, 5]);
+        assert!(matches!(statements[..], [Stmt::Print(_), Stmt::Print(_), Stmt::Function(_), Stmt::Print(_)]));
+    }
+}
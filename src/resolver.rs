@@ -0,0 +1,380 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use super::ast::*;
+use super::errors::{LoxError, ResolverError};
+use super::interpreter::Interpreter;
+use super::symbol::Symbol;
+use super::token::Token;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Static pass run between parsing and execution. It tells the interpreter how many
+/// scopes separate each variable reference from its declaration, and reports misuse
+/// of `return`, `this` and `super` before any code runs.
+pub struct Resolver<'a> {
+    /// Receives the resolved scope distances; `None` when only checking for errors.
+    interpreter: Option<&'a Interpreter>,
+    /// One map per nested local scope; the flag is whether the variable's
+    /// initializer has finished resolving.
+    scopes: RefCell<Vec<HashMap<Symbol, bool>>>,
+    current_function: Cell<FunctionType>,
+    current_class: Cell<ClassType>,
+    errors: RefCell<Vec<ResolverError>>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a Interpreter) -> Self {
+        Self::with_interpreter(Some(interpreter))
+    }
+
+    /// A resolver that only reports static errors, for backends that resolve
+    /// variables themselves such as the bytecode compiler.
+    pub fn checker() -> Self {
+        Self::with_interpreter(None)
+    }
+
+    fn with_interpreter(interpreter: Option<&'a Interpreter>) -> Self {
+        Self {
+            interpreter,
+            scopes: RefCell::new(Vec::new()),
+            current_function: Cell::new(FunctionType::None),
+            current_class: Cell::new(ClassType::None),
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn resolve(&self, statements: &[Stmt]) -> Result<(), Vec<ResolverError>> {
+        self.resolve_statements(statements);
+        self.finish()
+    }
+
+    pub fn resolve_expression(&self, expr: &Expr) -> Result<(), Vec<ResolverError>> {
+        self.resolve_expr(expr);
+        self.finish()
+    }
+
+    fn finish(&self) -> Result<(), Vec<ResolverError>> {
+        let errors = self.errors.take();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn resolve_statements(&self, statements: &[Stmt]) {
+        for statement in statements {
+            self.resolve_stmt(statement);
+        }
+    }
+
+    fn resolve_stmt(&self, stmt: &Stmt) {
+        // The resolver only ever records errors, so visiting cannot fail.
+        let _ = stmt.accept(self);
+    }
+
+    fn resolve_expr(&self, expr: &Expr) {
+        let _ = expr.accept(self);
+    }
+
+    fn resolve_function(&self, function: &FunctionStmt, function_type: FunctionType) {
+        let enclosing_function = self.current_function.replace(function_type);
+
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_statements(&function.body);
+        self.end_scope();
+
+        self.current_function.set(enclosing_function);
+    }
+
+    fn begin_scope(&self) {
+        self.scopes.borrow_mut().push(HashMap::new());
+    }
+
+    fn end_scope(&self) {
+        self.scopes.borrow_mut().pop();
+    }
+
+    fn declare(&self, name: &Token) {
+        let mut scopes = self.scopes.borrow_mut();
+        let Some(scope) = scopes.last_mut() else {
+            return;
+        };
+        if scope.contains_key(&name.lexeme.symbol()) {
+            self.error(ResolverError::AlreadyDeclared(name.clone()));
+        }
+        scope.insert(name.lexeme.symbol(), false);
+    }
+
+    fn define(&self, name: &Token) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(name.lexeme.symbol(), true);
+        }
+    }
+
+    /// Defines an implicit binding such as `this` or `super` in the innermost scope.
+    fn define_synthetic(&self, name: Symbol) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(name, true);
+        }
+    }
+
+    fn resolve_local(&self, id: ExprId, name: Symbol) {
+        let scopes = self.scopes.borrow();
+        for (depth, scope) in scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name) {
+                if let Some(interpreter) = self.interpreter {
+                    interpreter.resolve(id, depth);
+                }
+                return;
+            }
+        }
+    }
+
+    fn error(&self, error: ResolverError) {
+        self.errors.borrow_mut().push(error);
+    }
+}
+
+impl Visitor<()> for Resolver<'_> {
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.expression);
+        Ok(())
+    }
+
+    fn visit_literal_expr(&self, _expr: &LiteralExpr) -> Result<(), LoxError> {
+        Ok(())
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<(), LoxError> {
+        let in_own_initializer = self.scopes.borrow()
+            .last()
+            .and_then(|scope| scope.get(&expr.name.lexeme.symbol()))
+            == Some(&false);
+        if in_own_initializer {
+            self.error(ResolverError::ReadInOwnInitializer(expr.name.clone()));
+        }
+
+        self.resolve_local(expr.id, expr.name.lexeme.symbol());
+        Ok(())
+    }
+
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.value);
+        self.resolve_local(expr.id, expr.name.lexeme.symbol());
+        Ok(())
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.left);
+        self.resolve_expr(&expr.right);
+        Ok(())
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.callee);
+        for argument in &expr.arguments {
+            self.resolve_expr(argument);
+        }
+        Ok(())
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.object);
+        Ok(())
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<(), LoxError> {
+        self.resolve_expr(&expr.value);
+        self.resolve_expr(&expr.object);
+        Ok(())
+    }
+
+    fn visit_this_expr(&self, expr: &ThisExpr) -> Result<(), LoxError> {
+        if self.current_class.get() == ClassType::None {
+            self.error(ResolverError::ThisOutsideClass(expr.keyword.clone()));
+            return Ok(());
+        }
+        self.resolve_local(expr.id, Symbol::THIS);
+        Ok(())
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<(), LoxError> {
+        match self.current_class.get() {
+            ClassType::None => self.error(ResolverError::SuperOutsideClass(expr.keyword.clone())),
+            ClassType::Class => self.error(ResolverError::SuperWithoutSuperclass(expr.keyword.clone())),
+            ClassType::Subclass => {},
+        }
+        self.resolve_local(expr.id, Symbol::SUPER);
+        Ok(())
+    }
+}
+
+impl StmtVisitor<()> for Resolver<'_> {
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.expression);
+        Ok(())
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.expression);
+        Ok(())
+    }
+
+    fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<(), LoxError> {
+        self.declare(&stmt.name);
+        if let Some(initializer) = &stmt.initializer {
+            self.resolve_expr(initializer);
+        }
+        self.define(&stmt.name);
+        Ok(())
+    }
+
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<(), LoxError> {
+        self.begin_scope();
+        self.resolve_statements(&stmt.statements);
+        self.end_scope();
+        Ok(())
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.condition);
+        self.resolve_stmt(&stmt.then_branch);
+        if let Some(else_branch) = &stmt.else_branch {
+            self.resolve_stmt(else_branch);
+        }
+        Ok(())
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<(), LoxError> {
+        self.resolve_expr(&stmt.condition);
+        self.resolve_stmt(&stmt.body);
+        Ok(())
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<(), LoxError> {
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        self.resolve_function(stmt, FunctionType::Function);
+        Ok(())
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<(), LoxError> {
+        if self.current_function.get() == FunctionType::None {
+            self.error(ResolverError::TopLevelReturn(stmt.keyword.clone()));
+        }
+        if let Some(value) = &stmt.value {
+            if self.current_function.get() == FunctionType::Initializer {
+                self.error(ResolverError::ReturnFromInitializer(stmt.keyword.clone()));
+            }
+            self.resolve_expr(value);
+        }
+        Ok(())
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<(), LoxError> {
+        let enclosing_class = self.current_class.replace(ClassType::Class);
+
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+
+        if let Some(superclass) = &stmt.superclass {
+            if superclass.name.lexeme == stmt.name.lexeme {
+                self.error(ResolverError::InheritFromSelf(superclass.name.clone()));
+            }
+            self.current_class.set(ClassType::Subclass);
+            self.visit_variable_expr(superclass)?;
+
+            self.begin_scope();
+            self.define_synthetic(Symbol::SUPER);
+        }
+
+        self.begin_scope();
+        self.define_synthetic(Symbol::THIS);
+
+        for method in &stmt.methods {
+            let function_type = if method.name.lexeme.symbol() == Symbol::INIT {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, function_type);
+        }
+
+        self.end_scope();
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class.set(enclosing_class);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(source: &str) -> Result<(), Vec<ResolverError>> {
+        let statements = crate::parse(source.as_bytes()).unwrap();
+        Resolver::new(&Interpreter::new()).resolve(&statements)
+    }
+
+    #[test]
+    fn test_read_local_in_own_initializer() {
+        let errors = resolve("{ var a = a; }").unwrap_err();
+        assert!(matches!(&errors[..], [ResolverError::ReadInOwnInitializer(token)] if token.lexeme == "a"));
+    }
+
+    #[test]
+    fn test_global_redeclaration_is_allowed() {
+        assert!(resolve("var a; var a;").is_ok());
+    }
+
+    #[test]
+    fn test_top_level_return() {
+        let errors = resolve("return 1;").unwrap_err();
+        assert!(matches!(errors[..], [ResolverError::TopLevelReturn(_)]));
+    }
+
+    #[test]
+    fn test_this_outside_class() {
+        let errors = resolve("fun f() { return this; }").unwrap_err();
+        assert!(matches!(errors[..], [ResolverError::ThisOutsideClass(_)]));
+    }
+
+    #[test]
+    fn test_reports_every_error() {
+        let errors = resolve("super.m; class A < A {}").unwrap_err();
+        assert!(matches!(errors[..], [ResolverError::SuperOutsideClass(_), ResolverError::InheritFromSelf(_)]));
+    }
+}
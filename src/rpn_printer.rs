@@ -0,0 +1,213 @@
+use super::ast::*;
+use super::errors::LoxError;
+
+/// Prints expressions in reverse Polish notation, operands before their operator,
+/// so `(1 + 2) * (4 - 3)` becomes `1 2 + 4 3 - *`. Grouping disappears, which makes
+/// the precedence the parser actually chose explicit.
+///
+/// Statements print one per line with their expressions in RPN; nested bodies are
+/// indented.
+pub struct RpnPrinter;
+
+impl RpnPrinter {
+    pub fn print(&self, expr: &Expr) -> Result<String, LoxError> {
+        expr.accept(self)
+    }
+
+    pub fn print_program(&self, statements: &[Stmt]) -> Result<String, LoxError> {
+        let mut text = String::new();
+        for statement in statements {
+            text.push_str(&statement.accept(self)?);
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    fn postfix(&self, operands: Vec<&Expr>, operator: &str) -> Result<String, LoxError> {
+        let mut text = String::new();
+        for operand in operands {
+            text.push_str(&operand.accept(self)?);
+            text.push(' ');
+        }
+        text.push_str(operator);
+        Ok(text)
+    }
+
+    /// A header line followed by `body` indented. The statements of a block body are
+    /// indented directly rather than under a `block` line of their own.
+    fn nested(&self, header: String, body: &Stmt) -> Result<String, LoxError> {
+        match body {
+            Stmt::Block(block) => self.lines(header, &block.statements),
+            _ => Ok(format!("{header}\n{}", indent(&body.accept(self)?))),
+        }
+    }
+
+    fn lines(&self, header: String, statements: &[Stmt]) -> Result<String, LoxError> {
+        let mut text = header;
+        for statement in statements {
+            text.push('\n');
+            text.push_str(&indent(&statement.accept(self)?));
+        }
+        Ok(text)
+    }
+
+    /// `prefix` is `fun ` for a function declaration and empty for a method.
+    fn function(&self, prefix: &str, stmt: &FunctionStmt) -> Result<String, LoxError> {
+        let params: Vec<&str> = stmt.params.iter().map(|param| param.lexeme.as_str()).collect();
+        self.lines(format!("{prefix}{}({})", stmt.name.lexeme, params.join(", ")), &stmt.body)
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.lines().map(|line| format!("  {line}")).collect::<Vec<_>>().join("\n")
+}
+
+impl Visitor<String> for RpnPrinter {
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<String, LoxError> {
+        self.postfix(vec![&expr.left, &expr.right], &expr.operator.lexeme)
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<String, LoxError> {
+        expr.expression.accept(self)
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<String, LoxError> {
+        Ok(expr.source_text())
+    }
+
+    /// Negation prints as `neg` so it cannot be mistaken for subtraction.
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<String, LoxError> {
+        let operator = if expr.operator.lexeme == "-" { "neg" } else { &expr.operator.lexeme };
+        self.postfix(vec![&expr.right], operator)
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> Result<String, LoxError> {
+        Ok(expr.name.lexeme.to_string())
+    }
+
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> Result<String, LoxError> {
+        Ok(format!("{} {} =", expr.name.lexeme, expr.value.accept(self)?))
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<String, LoxError> {
+        self.postfix(vec![&expr.left, &expr.right], &expr.operator.lexeme)
+    }
+
+    /// The call operator carries its argument count, as `call/2`.
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<String, LoxError> {
+        let mut operands = vec![expr.callee.as_ref()];
+        operands.extend(expr.arguments.iter());
+        self.postfix(operands, &format!("call/{}", expr.arguments.len()))
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<String, LoxError> {
+        self.postfix(vec![&expr.object], &format!(".{}", expr.name.lexeme))
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<String, LoxError> {
+        self.postfix(vec![&expr.object, &expr.value], &format!(".{} =", expr.name.lexeme))
+    }
+
+    fn visit_this_expr(&self, _expr: &ThisExpr) -> Result<String, LoxError> {
+        Ok("this".to_string())
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> Result<String, LoxError> {
+        Ok(format!("super.{}", expr.method.lexeme))
+    }
+}
+
+impl StmtVisitor<String> for RpnPrinter {
+    fn visit_expression_stmt(&self, stmt: &ExpressionStmt) -> Result<String, LoxError> {
+        stmt.expression.accept(self)
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) -> Result<String, LoxError> {
+        self.postfix(vec![&stmt.expression], "print")
+    }
+
+    fn visit_var_stmt(&self, stmt: &VarStmt) -> Result<String, LoxError> {
+        Ok(match &stmt.initializer {
+            Some(initializer) => format!("{} {} var", stmt.name.lexeme, initializer.accept(self)?),
+            None => format!("{} var", stmt.name.lexeme),
+        })
+    }
+
+    fn visit_block_stmt(&self, stmt: &BlockStmt) -> Result<String, LoxError> {
+        self.lines("block".to_string(), &stmt.statements)
+    }
+
+    fn visit_if_stmt(&self, stmt: &IfStmt) -> Result<String, LoxError> {
+        let mut text = self.nested(format!("if ({})", stmt.condition.accept(self)?), &stmt.then_branch)?;
+        if let Some(else_branch) = &stmt.else_branch {
+            text.push('\n');
+            text.push_str(&self.nested("else".to_string(), else_branch)?);
+        }
+        Ok(text)
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) -> Result<String, LoxError> {
+        self.nested(format!("while ({})", stmt.condition.accept(self)?), &stmt.body)
+    }
+
+    fn visit_function_stmt(&self, stmt: &FunctionStmt) -> Result<String, LoxError> {
+        self.function("fun ", stmt)
+    }
+
+    fn visit_return_stmt(&self, stmt: &ReturnStmt) -> Result<String, LoxError> {
+        match &stmt.value {
+            Some(value) => self.postfix(vec![value], "return"),
+            None => Ok("return".to_string()),
+        }
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<String, LoxError> {
+        let mut text = format!("class {}", stmt.name.lexeme);
+        if let Some(superclass) = &stmt.superclass {
+            text.push_str(&format!(" < {}", superclass.name.lexeme));
+        }
+        for method in &stmt.methods {
+            text.push('\n');
+            text.push_str(&indent(&self.function("", method)?));
+        }
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn print(source: &str) -> String {
+        RpnPrinter.print_program(&parse(source.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert_eq!(print("(1 + 2) * (4 - 3);"), "1 2 + 4 3 - *\n");
+        assert_eq!(print("1 + 2 * 3;"), "1 2 3 * +\n");
+        assert_eq!(print("-a - !b or c and nil;"), "a neg b ! - c nil and or\n");
+        assert_eq!(print("x = o.f(1, \"s\").g = true;"), "x o .f 1 \"s\" call/2 true .g = =\n");
+    }
+
+    #[test]
+    fn test_statements() {
+        let source = "\
+class B < A { m(x) { return super.m(x); } }
+for (var i = 0; i < 3; i = i + 1) if (i > 1) print i; else { print -i; }";
+        assert_eq!(print(source), "\
+class B < A
+  m(x)
+    super.m x call/1 return
+block
+  i 0 var
+  while (i 3 <)
+    if (i 1 >)
+      i print
+    else
+      i neg print
+    i i 1 + =
+");
+    }
+}
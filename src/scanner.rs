@@ -1,14 +1,47 @@
-use crate::token::{Token, Literal};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use crate::span::Span;
+use crate::symbol::Symbol;
+use crate::token::{Comment, Lexeme, Token, Literal};
 use super::token_type::TokenType;
 use super::errors::TokenError;
 
+static KEYWORDS: LazyLock<HashMap<&'static str, TokenType>> = LazyLock::new(|| {
+    HashMap::from([
+        ("and", TokenType::And),
+        ("class", TokenType::Class),
+        ("else", TokenType::Else),
+        ("false", TokenType::False),
+        ("for", TokenType::For),
+        ("fun", TokenType::Fun),
+        ("if", TokenType::If),
+        ("nil", TokenType::Nil),
+        ("or", TokenType::Or),
+        ("print", TokenType::Print),
+        ("return", TokenType::Return),
+        ("super", TokenType::Super),
+        ("this", TokenType::This),
+        ("true", TokenType::True),
+        ("var", TokenType::Var),
+        ("while", TokenType::While),
+    ])
+});
+
 #[derive(Debug, Clone)]
 pub struct Scanner {
     source: Vec<u8>,
     tokens: Vec<Token>,
+    comments: Vec<Comment>,
     start: u64,
     current: u64,
     line: u64,
+    /// Byte offset where the current line begins.
+    line_start: u64,
+    /// Value of `line_start` when the current token began.
+    token_line_start: u64,
+    /// Whether whitespace and comments are emitted as trivia tokens.
+    lossless: bool,
 }
 
 impl Scanner {
@@ -16,25 +49,50 @@ impl Scanner {
         Scanner {
             source,
             tokens: Vec::new(),
+            comments: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            token_line_start: 0,
+            lossless: false,
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Vec<Token> {
+    /// Emits `Whitespace` and `Comment` trivia tokens as well, so that concatenating the
+    /// lexemes of the scanned tokens reproduces the source exactly.
+    pub fn lossless(mut self) -> Self {
+        self.lossless = true;
+        self
+    }
+
+    /// Scans the whole source. Every lexical error is collected rather than aborting the
+    /// scan, and leaves a `TokenType::Error` token in the stream where it happened.
+    pub fn scan_tokens(&mut self) -> (Vec<Token>, Vec<TokenError>) {
+        let mut errors = Vec::new();
         loop {
             if self.is_at_end() {
                 break;
             }
             self.start = self.current;
+            self.token_line_start = self.line_start;
             if let Err(e) = self.scan_token() {
-                println!("{e}");
+                self.add_token(TokenType::Error);
+                errors.push(e);
             }
         }
-        let eof_token = Token::new(TokenType::Eof, "".to_string(), None, self.line);
+        self.start = self.current;
+        self.token_line_start = self.line_start;
+        let end = self.current as usize;
+        let eof_token = Token::new(TokenType::Eof, Lexeme::Fixed(""), None, self.line)
+            .with_position(self.column(), Span::new(end, end));
         self.tokens.push(eof_token);
-        self.tokens.clone()
+        (std::mem::take(&mut self.tokens), errors)
+    }
+
+    /// Comments seen by the last call to `scan_tokens`, in source order.
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
     }
 
     fn is_at_end(&self) -> bool {
@@ -85,12 +143,12 @@ impl Scanner {
             '/' => {
                 if self.match_next_lexeme('/') {
                     loop {
-                        if self.peek() != '\n' && self.is_at_end() {
-                            self.advance();
-                        } else {
+                        if self.peek() == '\n' || self.is_at_end() {
                             break;
                         }
+                        self.advance();
                     }
+                    self.add_comment();
                     return Ok(())
                 } else if self.match_next_lexeme('*') {
                     return self.block_comment();
@@ -98,21 +156,23 @@ impl Scanner {
                     TokenType::Slash
                 }
             },
-            ' ' | '\r' | '\t' => return Ok(()),
-            '\n' => {
-                self.line += 1;
+            ' ' | '\r' | '\t' | '\n' => {
+                if c == '\n' {
+                    self.newline();
+                }
+                if self.lossless {
+                    while matches!(self.peek(), ' ' | '\r' | '\t' | '\n') {
+                        if self.advance() == b'\n' {
+                            self.newline();
+                        }
+                    }
+                    self.add_token(TokenType::Whitespace);
+                }
                 return Ok(())
             },
             '"' => {
                 return self.string()
             },
-            'o' => {
-                if self.peek() == 'r' {
-                    TokenType::Or
-                } else {
-                    return Ok(());
-                }
-            }
             _ => {
                 if c.is_ascii_digit() {
                     self.number();
@@ -121,7 +181,15 @@ impl Scanner {
                     self.identifier();
                     return Ok(());
                 } else {
-                    return Err(TokenError::InvalidToken(self.line, c));
+                    // Take the rest of a multi-byte character so the error token covers all of it.
+                    while !self.is_at_end() && self.source[self.current as usize] & 0xC0 == 0x80 {
+                        self.current += 1;
+                    }
+                    let c = String::from_utf8_lossy(&self.source[self.start as usize..self.current as usize])
+                        .chars()
+                        .next()
+                        .unwrap_or(c);
+                    return Err(TokenError::InvalidToken(self.line, c, self.span()));
                 }
             },
         };
@@ -142,11 +210,44 @@ impl Scanner {
     fn add_token_with_literal(&mut self, token_type: TokenType, literal: Option<Literal>) {
         let start = self.start as usize;
         let current = self.current as usize;
-        let text = String::from_utf8(self.source[start..current].to_vec()).expect("Invalid UTF-8");
-        let new_token = Token::new(token_type, text, literal, self.line);
+        let text = String::from_utf8_lossy(&self.source[start..current]);
+        let lexeme = match (&token_type, token_type.fixed_text()) {
+            (TokenType::Identifier | TokenType::String, _) => Lexeme::Interned(Symbol::intern(&text)),
+            (_, Some(fixed)) => Lexeme::Fixed(fixed),
+            (_, None) => Lexeme::from(text.into_owned()),
+        };
+        let new_token = Token::new(token_type, lexeme, literal, self.line)
+            .with_position(self.column(), self.span());
         self.tokens.push(new_token);
     }
 
+    fn add_comment(&mut self) {
+        let text = String::from_utf8_lossy(&self.source[self.start as usize..self.current as usize]).into_owned();
+        self.comments.push(Comment {
+            text,
+            span: self.span(),
+        });
+        if self.lossless {
+            self.add_token(TokenType::Comment);
+        }
+    }
+
+    /// Records that a `\n` was just consumed.
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
+    fn span(&self) -> Span {
+        Span::new(self.start as usize, self.current as usize)
+    }
+
+    /// One-based character column where the current token starts.
+    fn column(&self) -> u64 {
+        let prefix = &self.source[self.token_line_start as usize..self.start as usize];
+        prefix.iter().filter(|&&b| b & 0xC0 != 0x80).count() as u64 + 1
+    }
+
     fn match_next_lexeme(&mut self, expected: char) -> bool {
         if self.is_at_end() {
             return false;
@@ -185,19 +286,25 @@ impl Scanner {
             if self.peek() == '"' || self.is_at_end() {
                 break;
             }
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance() == b'\n' {
+                self.newline();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            return Err(TokenError::UnterminatedString(self.line));
+            return Err(TokenError::UnterminatedString(self.line, self.span()));
         }
         self.advance();
         let start = (self.start + 1) as usize;
         let current = (self.current - 1) as usize;
-        let value = String::from_utf8(self.source[start..current].to_vec()).expect("Invalid UTF-8");
+        let value = match std::str::from_utf8(&self.source[start..current]) {
+            Ok(value) => Symbol::intern(value),
+            Err(error) => {
+                let invalid = start + error.valid_up_to();
+                let span = Span::new(invalid, invalid + error.error_len().unwrap_or(1));
+                return Err(TokenError::InvalidUtf8(self.line, span));
+            },
+        };
         self.add_token_with_literal(TokenType::String, Some(Literal::Str(value)));
         Ok(())
     }
@@ -207,15 +314,18 @@ impl Scanner {
             if self.peek() == '*' && self.peek_next() == '/' || self.is_at_end() {
                 break;
             }
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance() == b'\n' {
+                self.newline();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            return Err(TokenError::UnterminatedBlockComment(self.line))
+            return Err(TokenError::UnterminatedBlockComment(self.line, self.span()))
         }
+        // Consume the closing "*/".
+        self.advance();
+        self.advance();
+        self.add_comment();
         Ok(())
     }
 
@@ -248,10 +358,7 @@ impl Scanner {
             self.advance();
         }
         let text = String::from_utf8(self.source[self.start as usize..self.current as usize].to_vec()).expect("Invalid UTF-8");
-        let token = match serde_json::from_str::<TokenType>(text.as_str()) {
-            Ok(token_type) => token_type,
-            Err(_) => TokenType::Identifier
-        };
+        let token = KEYWORDS.get(text.as_str()).cloned().unwrap_or(TokenType::Identifier);
         self.add_token(token);
     }
 }
@@ -272,50 +379,239 @@ mod tests {
     #[test]
     fn test_and() {
         let mut scanner = construct_scanner("and");
-        let tokens = scanner.scan_tokens();
-        assert_eq!(tokens[0].token_type, TokenType::Identifier);
+        let (tokens, _) = scanner.scan_tokens();
+        assert_eq!(tokens[0].token_type, TokenType::And);
         assert_eq!(tokens[0].lexeme, "and");
     }
 
+    #[test]
+    fn test_keywords() {
+        let keywords = [
+            ("and", TokenType::And),
+            ("class", TokenType::Class),
+            ("else", TokenType::Else),
+            ("false", TokenType::False),
+            ("for", TokenType::For),
+            ("fun", TokenType::Fun),
+            ("if", TokenType::If),
+            ("nil", TokenType::Nil),
+            ("or", TokenType::Or),
+            ("print", TokenType::Print),
+            ("return", TokenType::Return),
+            ("super", TokenType::Super),
+            ("this", TokenType::This),
+            ("true", TokenType::True),
+            ("var", TokenType::Var),
+            ("while", TokenType::While),
+        ];
+        for (source, token_type) in keywords {
+            let mut scanner = construct_scanner(source);
+            let (tokens, _) = scanner.scan_tokens();
+            assert_eq!(tokens.len(), 2, "{source}");
+            assert_eq!(tokens[0].token_type, token_type, "{source}");
+            assert_eq!(tokens[0].lexeme, source);
+        }
+    }
+
+    #[test]
+    fn test_identifiers_with_keyword_prefix() {
+        let mut scanner = construct_scanner("orchid android classy fortune variable _if If");
+        let (tokens, _) = scanner.scan_tokens();
+        let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme.as_str()).collect();
+        assert_eq!(lexemes, ["orchid", "android", "classy", "fortune", "variable", "_if", "If", ""]);
+        for token in &tokens[..tokens.len() - 1] {
+            assert_tokens(token.clone(), TokenType::Identifier);
+        }
+    }
+
     #[test]
     fn test_left_paren() {
         let mut scanner = construct_scanner("(");
-        let tokens = scanner.scan_tokens();
+        let (tokens, _) = scanner.scan_tokens();
         assert_tokens(tokens[0].clone(), TokenType::LeftParen);
     }
 
     #[test]
     fn test_right_paren() {
         let mut scanner = construct_scanner(")");
-        let tokens = scanner.scan_tokens();
+        let (tokens, _) = scanner.scan_tokens();
         assert_tokens(tokens[0].clone(), TokenType::RightParen);
     }
 
     #[test]
     fn test_left_brace() {
         let mut scanner = construct_scanner("{");
-        let tokens = scanner.scan_tokens();
+        let (tokens, _) = scanner.scan_tokens();
         assert_tokens(tokens[0].clone(), TokenType::LeftBrace);
     }
 
     #[test]
     fn test_right_brace() {
         let mut scanner = construct_scanner("}");
-        let tokens = scanner.scan_tokens();
+        let (tokens, _) = scanner.scan_tokens();
         assert_tokens(tokens[0].clone(), TokenType::RightBrace);
     }
 
     #[test]
     fn test_comma() {
         let mut scanner = construct_scanner(",");
-        let tokens = scanner.scan_tokens();
+        let (tokens, _) = scanner.scan_tokens();
         assert_tokens(tokens[0].clone(), TokenType::Comma);
     }
 
     #[test]
     fn test_dot() {
         let mut scanner = construct_scanner(".");
-        let tokens = scanner.scan_tokens();
+        let (tokens, _) = scanner.scan_tokens();
         assert_tokens(tokens[0].clone(), TokenType::Dot);
     }
+
+    #[test]
+    fn test_token_types() {
+        use TokenType::*;
+
+        let cases: Vec<(&str, Vec<TokenType>)> = vec![
+            ("(", vec![LeftParen]),
+            (")", vec![RightParen]),
+            ("{", vec![LeftBrace]),
+            ("}", vec![RightBrace]),
+            (",", vec![Comma]),
+            (".", vec![Dot]),
+            ("-", vec![Minus]),
+            ("+", vec![Plus]),
+            (";", vec![Semicolon]),
+            ("/", vec![Slash]),
+            ("*", vec![Star]),
+            ("!", vec![Bang]),
+            ("!=", vec![BangEqual]),
+            ("=", vec![Equal]),
+            ("==", vec![EqualEqual]),
+            (">", vec![Greater]),
+            (">=", vec![GreaterEqual]),
+            ("<", vec![Less]),
+            ("<=", vec![LessEqual]),
+            ("name", vec![Identifier]),
+            ("\"text\"", vec![String]),
+            ("12.5", vec![Number]),
+            ("and", vec![And]),
+            ("class", vec![Class]),
+            ("else", vec![Else]),
+            ("false", vec![False]),
+            ("fun", vec![Fun]),
+            ("for", vec![For]),
+            ("if", vec![If]),
+            ("nil", vec![Nil]),
+            ("or", vec![Or]),
+            ("print", vec![Print]),
+            ("return", vec![Return]),
+            ("super", vec![Super]),
+            ("this", vec![This]),
+            ("true", vec![True]),
+            ("var", vec![Var]),
+            ("while", vec![While]),
+            ("", vec![]),
+            ("!===<=>=", vec![BangEqual, EqualEqual, LessEqual, GreaterEqual]),
+            ("= =", vec![Equal, Equal]),
+            ("a/b", vec![Identifier, Slash, Identifier]),
+            ("1 // comment\n2", vec![Number, Number]),
+            ("// comment at end", vec![]),
+            ("1 /* block\ncomment */ 2", vec![Number, Number]),
+            ("/**/", vec![]),
+            ("a.b(c)", vec![Identifier, Dot, Identifier, LeftParen, Identifier, RightParen]),
+            ("x.1", vec![Identifier, Dot, Number]),
+            ("1.", vec![Number, Dot]),
+        ];
+
+        for (source, mut expected) in cases {
+            expected.push(Eof);
+            let mut scanner = construct_scanner(source);
+            let token_types: Vec<TokenType> = scanner.scan_tokens().0.into_iter().map(|t| t.token_type).collect();
+            assert_eq!(token_types, expected, "source: {source:?}");
+        }
+    }
+
+    #[test]
+    fn test_comments_track_lines() {
+        let mut scanner = construct_scanner("// one\n/* two\nthree */ a\nb");
+        let (tokens, _) = scanner.scan_tokens();
+        let lines: Vec<u64> = tokens.iter().map(|t| t.line).collect();
+        assert_eq!(lines, [3, 4, 4]);
+        let comments: Vec<(&str, Span)> = scanner.comments().iter().map(|c| (c.text.as_str(), c.span)).collect();
+        assert_eq!(comments, [("// one", Span::new(0, 6)), ("/* two\nthree */", Span::new(7, 22))]);
+    }
+
+    #[test]
+    fn test_lossless_emits_trivia() {
+        let source = "var a;  // note\n\t/* block */print a;\r\n";
+        let mut scanner = Scanner::new(source.as_bytes().to_vec()).lossless();
+        let (tokens, _) = scanner.scan_tokens();
+        let text: String = tokens.iter().map(|t| t.lexeme.as_str()).collect();
+        assert_eq!(text, source);
+        let trivia: Vec<&str> = tokens.iter().filter(|t| t.token_type.is_trivia()).map(|t| t.lexeme.as_str()).collect();
+        assert_eq!(trivia, [" ", "  ", "// note", "\n\t", "/* block */", " ", "\r\n"]);
+
+        let (tokens, _) = construct_scanner(source).scan_tokens();
+        assert!(tokens.iter().all(|t| !t.token_type.is_trivia()));
+    }
+
+    #[test]
+    fn test_only_names_and_strings_are_interned() {
+        let (tokens, _) = Scanner::new(b"var a = \"s\" + 1.5; // note".to_vec()).lossless().scan_tokens();
+        let kinds: Vec<&str> = tokens.iter().map(|t| match t.lexeme {
+            Lexeme::Interned(_) => "interned",
+            Lexeme::Fixed(_) => "fixed",
+            Lexeme::Owned(_) => "owned",
+        }).collect();
+        assert_eq!(kinds, [
+            "fixed", "owned", "interned", "owned", "fixed", "owned", "interned", "owned",
+            "fixed", "owned", "owned", "fixed", "owned", "owned", "fixed",
+        ]);
+        assert_eq!(tokens[2].lexeme.symbol(), Symbol::intern("a"));
+    }
+
+    #[test]
+    fn test_token_positions() {
+        let mut scanner = construct_scanner("var x = \"a\nb\";\n  x;");
+        let (tokens, _) = scanner.scan_tokens();
+        let positions: Vec<(u64, Span)> = tokens.iter().map(|t| (t.column, t.span)).collect();
+        assert_eq!(positions, [
+            (1, Span::new(0, 3)),
+            (5, Span::new(4, 5)),
+            (7, Span::new(6, 7)),
+            (9, Span::new(8, 13)),
+            (3, Span::new(13, 14)),
+            (3, Span::new(17, 18)),
+            (4, Span::new(18, 19)),
+            (5, Span::new(19, 19)),
+        ]);
+    }
+
+    #[test]
+    fn test_literals() {
+        let mut scanner = construct_scanner("\"hi\" 3.25");
+        let (tokens, _) = scanner.scan_tokens();
+        assert!(matches!(&tokens[0].litteral, Some(Literal::Str(s)) if s == "hi"));
+        assert!(matches!(tokens[1].litteral, Some(Literal::Number(n)) if n == 3.25));
+    }
+
+    #[test]
+    fn test_errors_are_collected() {
+        let mut scanner = construct_scanner("a # b é \"c");
+        let (tokens, errors) = scanner.scan_tokens();
+        let types: Vec<TokenType> = tokens.iter().map(|t| t.token_type.clone()).collect();
+        assert_eq!(types, vec![TokenType::Identifier, TokenType::Error, TokenType::Identifier, TokenType::Error, TokenType::Error, TokenType::Eof]);
+        assert_eq!(tokens[3].lexeme, "é");
+        assert!(matches!(
+            errors[..],
+            [TokenError::InvalidToken(1, '#', _), TokenError::InvalidToken(1, 'é', _), TokenError::UnterminatedString(1, _)]
+        ));
+    }
+
+    #[test]
+    fn test_invalid_utf8_in_string() {
+        let mut scanner = Scanner::new(b"print \"ok\xff\";".to_vec());
+        let (tokens, errors) = scanner.scan_tokens();
+        assert_eq!(tokens[1].token_type, TokenType::Error);
+        assert!(matches!(errors[..], [TokenError::InvalidUtf8(1, span)] if span == Span::new(9, 10)));
+    }
 }
@@ -0,0 +1,147 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+/// Half-open range of byte offsets into the source, `start..end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self {
+            start,
+            end,
+        }
+    }
+
+    /// Smallest span covering both `self` and `other`.
+    pub fn merge(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// One-based line and column; columns count characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Converts byte offsets in a source file into line/column positions. The source is
+/// kept as raw bytes, so offsets stay right even if it isn't valid UTF-8.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    source: Vec<u8>,
+    line_starts: Vec<usize>,
+}
+
+impl SourceMap {
+    pub fn new(source: &[u8]) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.iter().enumerate().filter(|&(_, &byte)| byte == b'\n').map(|(i, _)| i + 1));
+        Self {
+            source: source.to_vec(),
+            line_starts,
+        }
+    }
+
+    pub fn source(&self) -> &[u8] {
+        &self.source
+    }
+
+    /// The source text in `span`, with invalid UTF-8 shown as U+FFFD.
+    pub fn text(&self, span: Span) -> Cow<'_, str> {
+        let end = span.end.min(self.source.len());
+        String::from_utf8_lossy(&self.source[span.start.min(end)..end])
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    pub fn location(&self, offset: usize) -> Location {
+        let offset = offset.min(self.source.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let line_start = self.line_starts[line];
+        let column = self.text(Span::new(line_start, offset)).chars().count();
+        Location {
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+
+    /// Byte range of a one-based line, excluding its line terminator.
+    pub fn line_span(&self, line: usize) -> Span {
+        let start = self.line_starts[line - 1];
+        let end = self.line_starts.get(line).map_or(self.source.len(), |next| next - 1);
+        let end = if end > start && self.source[end - 1] == b'\r' { end - 1 } else { end };
+        Span::new(start, end)
+    }
+
+    /// The text of a one-based line, with invalid UTF-8 shown as U+FFFD.
+    pub fn line_text(&self, line: usize) -> Cow<'_, str> {
+        self.text(self.line_span(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location() {
+        let map = SourceMap::new(b"var a;\nprint a;\n");
+        assert_eq!(map.location(0), Location { line: 1, column: 1 });
+        assert_eq!(map.location(4), Location { line: 1, column: 5 });
+        assert_eq!(map.location(7), Location { line: 2, column: 1 });
+        assert_eq!(map.location(13), Location { line: 2, column: 7 });
+        assert_eq!(map.location(16), Location { line: 3, column: 1 });
+    }
+
+    #[test]
+    fn test_location_counts_characters() {
+        let map = SourceMap::new("\"héllo\" x".as_bytes());
+        assert_eq!(map.location(9), Location { line: 1, column: 9 });
+    }
+
+    #[test]
+    fn test_location_after_invalid_utf8() {
+        let map = SourceMap::new(b"// \xff\xfe\nprint 1;\n");
+        assert_eq!(map.location(6), Location { line: 2, column: 1 });
+        assert_eq!(map.location(12), Location { line: 2, column: 7 });
+        assert_eq!(map.line_text(1), "// \u{fffd}\u{fffd}");
+    }
+
+    #[test]
+    fn test_line_text() {
+        let map = SourceMap::new(b"one\r\ntwo\nthree");
+        assert_eq!(map.line_count(), 3);
+        assert_eq!(map.line_text(1), "one");
+        assert_eq!(map.line_text(2), "two");
+        assert_eq!(map.line_text(3), "three");
+    }
+
+    #[test]
+    fn test_merge() {
+        assert_eq!(Span::new(4, 6).merge(Span::new(0, 2)), Span::new(0, 6));
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{LazyLock, Mutex, PoisonError};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An interned string: identifiers and string literals become symbols when scanned. Every distinct string is stored once for the life of the process,
+/// so copying a symbol is free, comparing two is an integer comparison and hashing
+/// one hashes its id rather than its text. A symbol also points at its text, so
+/// only interning takes the interner's lock; reading the text never does.
+///
+/// Symbols serialize as their text, so JSON dumps and `.loxc` files do not depend
+/// on the order strings happened to be interned in.
+#[derive(Clone, Copy)]
+pub struct Symbol {
+    id: u32,
+    text: &'static str,
+}
+
+struct Interner {
+    ids: HashMap<&'static str, Symbol>,
+}
+
+/// Names the interpreters look up themselves, interned up front so that their
+/// symbols can be constants.
+const PREDEFINED: [Symbol; 4] = [Symbol::INIT, Symbol::THIS, Symbol::SUPER, Symbol::CLOCK];
+
+impl Interner {
+    fn new() -> Self {
+        Interner { ids: PREDEFINED.iter().map(|&symbol| (symbol.text, symbol)).collect() }
+    }
+}
+
+/// Interned strings are never freed, which is why only names and string literals
+/// are interned, and not other lexemes or strings built at runtime.
+static INTERNER: LazyLock<Mutex<Interner>> = LazyLock::new(|| Mutex::new(Interner::new()));
+
+impl Symbol {
+    pub const INIT: Symbol = Symbol { id: 0, text: "init" };
+    pub const THIS: Symbol = Symbol { id: 1, text: "this" };
+    pub const SUPER: Symbol = Symbol { id: 2, text: "super" };
+    pub const CLOCK: Symbol = Symbol { id: 3, text: "clock" };
+
+    pub fn intern(string: &str) -> Self {
+        let mut interner = INTERNER.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(&symbol) = interner.ids.get(string) {
+            return symbol;
+        }
+        let text: &'static str = Box::leak(string.into());
+        let id = u32::try_from(interner.ids.len()).expect("fewer than 2^32 distinct strings");
+        let symbol = Symbol { id, text };
+        interner.ids.insert(text, symbol);
+        symbol
+    }
+
+    /// The predefined symbol for `string`, found without taking the interner's lock.
+    pub fn predefined(string: &str) -> Option<Symbol> {
+        PREDEFINED.iter().find(|symbol| symbol.text == string).copied()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.text
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(string: &str) -> Self {
+        Symbol::intern(string)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(string: String) -> Self {
+        Symbol::intern(&string)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Symbol::intern(&String::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_strings_share_a_symbol() {
+        let a = Symbol::intern("interned");
+        let b = Symbol::from(String::from("interned"));
+        assert_eq!(a, b);
+        assert!(std::ptr::eq(a.as_str(), b.as_str()));
+        assert_ne!(a, Symbol::intern("other"));
+        assert_eq!(a, "interned");
+        assert_eq!(format!("{a} {a:?}"), "interned \"interned\"");
+        assert_eq!(serde_json::to_string(&a).unwrap(), "\"interned\"");
+        assert_eq!(serde_json::from_str::<Symbol>("\"interned\"").unwrap(), a);
+        assert_eq!(Symbol::intern("super"), Symbol::SUPER);
+        assert_eq!(Symbol::INIT.as_str(), "init");
+    }
+}
@@ -1,10 +1,16 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::span::Span;
+use super::symbol::Symbol;
 use super::token_type::TokenType;
 use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Literal {
-    Identifier(String),
-    Str(String),
+    Identifier(Symbol),
+    Str(Symbol),
     Number(f64),
     True,
     False,
@@ -22,23 +28,140 @@ impl fmt::Display for Literal {
     }
 }
 
-#[derive(Debug, Clone)]
+/// A `//` or `/* */` comment, kept aside by the scanner for tools that print
+/// source back out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    /// The comment including its delimiters.
+    pub text: String,
+    pub span: Span,
+}
+
+/// The source text of a token. Interned strings live until the process exits, so
+/// only identifiers and string literals, which the interpreters look up by symbol,
+/// are interned. Keywords and punctuation borrow their fixed text, and anything else,
+/// such as whitespace, comments and numbers, owns its text.
+#[derive(Clone)]
+pub enum Lexeme {
+    Interned(Symbol),
+    Fixed(&'static str),
+    Owned(Rc<str>),
+}
+
+impl Lexeme {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Lexeme::Interned(symbol) => symbol.as_str(),
+            Lexeme::Fixed(text) => text,
+            Lexeme::Owned(text) => text,
+        }
+    }
+
+    /// The symbol for this text, such as a variable name. Only lexemes that were not
+    /// interned when scanned, like `this`, need a lookup.
+    pub fn symbol(&self) -> Symbol {
+        match self {
+            Lexeme::Interned(symbol) => *symbol,
+            _ => Symbol::predefined(self).unwrap_or_else(|| Symbol::intern(self)),
+        }
+    }
+}
+
+impl Deref for Lexeme {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for Lexeme {
+    fn eq(&self, other: &Lexeme) -> bool {
+        match (self, other) {
+            (Lexeme::Interned(a), Lexeme::Interned(b)) => a == b,
+            _ => self.as_str() == other.as_str(),
+        }
+    }
+}
+
+impl PartialEq<&str> for Lexeme {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl From<Symbol> for Lexeme {
+    fn from(symbol: Symbol) -> Self {
+        Lexeme::Interned(symbol)
+    }
+}
+
+impl From<&str> for Lexeme {
+    fn from(text: &str) -> Self {
+        Lexeme::Owned(text.into())
+    }
+}
+
+impl From<String> for Lexeme {
+    fn from(text: String) -> Self {
+        Lexeme::Owned(text.into())
+    }
+}
+
+impl fmt::Display for Lexeme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Lexeme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Serialize for Lexeme {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Deserialized lexemes own their text; [`Lexeme::symbol`] interns names on use.
+impl<'de> Deserialize<'de> for Lexeme {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Lexeme::from(String::deserialize(deserializer)?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
+    pub lexeme: Lexeme,
+    #[serde(rename = "literal")]
     pub litteral: Option<Literal>,
     pub line: u64,
+    /// One-based character column of the first character of the lexeme.
+    pub column: u64,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, litteral: Option<Literal>, line: u64) -> Self {
+    pub fn new(token_type: TokenType, lexeme: impl Into<Lexeme>, litteral: Option<Literal>, line: u64) -> Self {
         Self {
             token_type,
-            lexeme,
+            lexeme: lexeme.into(),
             litteral,
             line,
+            column: 0,
+            span: Span::default(),
         }
     }
+
+    pub fn with_position(mut self, column: u64, span: Span) -> Self {
+        self.column = column;
+        self.span = span;
+        self
+    }
 }
 
 impl std::fmt::Display for Token {
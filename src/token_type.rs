@@ -3,9 +3,9 @@ use serde::{Serialize, Deserialize};
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum TokenType {
-    #[serde(rename = "[")]
+    #[serde(rename = "(")]
     LeftParen,
-    #[serde(rename = "]")]
+    #[serde(rename = ")")]
     RightParen,
     #[serde(rename = "{")]
     LeftBrace,
@@ -37,7 +37,7 @@ pub enum TokenType {
     Greater,
     #[serde(rename = ">=")]
     GreaterEqual,
-    #[serde(rename = "-")]
+    #[serde(rename = "<")]
     Less,
     #[serde(rename = "<=")]
     LessEqual,
@@ -60,6 +60,69 @@ pub enum TokenType {
     True,
     Var,
     While,
+    /// Stands in for source the scanner could not tokenize; the error itself is
+    /// reported separately.
+    Error,
+    /// A run of spaces, tabs and line breaks; only emitted by a lossless scanner.
+    Whitespace,
+    /// A `//` or `/* */` comment; only emitted by a lossless scanner.
+    Comment,
     Eof,
 }
 
+impl TokenType {
+    /// Whitespace and comments, which carry no meaning for the parser.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, TokenType::Whitespace | TokenType::Comment)
+    }
+
+    /// The text every token of this type has, for punctuation and keywords.
+    pub fn fixed_text(&self) -> Option<&'static str> {
+        let text = match self {
+            TokenType::LeftParen => "(",
+            TokenType::RightParen => ")",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+            TokenType::Comma => ",",
+            TokenType::Dot => ".",
+            TokenType::Minus => "-",
+            TokenType::Plus => "+",
+            TokenType::Semicolon => ";",
+            TokenType::Slash => "/",
+            TokenType::Star => "*",
+            TokenType::Bang => "!",
+            TokenType::BangEqual => "!=",
+            TokenType::Equal => "=",
+            TokenType::EqualEqual => "==",
+            TokenType::Greater => ">",
+            TokenType::GreaterEqual => ">=",
+            TokenType::Less => "<",
+            TokenType::LessEqual => "<=",
+            TokenType::And => "and",
+            TokenType::Class => "class",
+            TokenType::Else => "else",
+            TokenType::False => "false",
+            TokenType::Fun => "fun",
+            TokenType::For => "for",
+            TokenType::If => "if",
+            TokenType::Nil => "nil",
+            TokenType::Or => "or",
+            TokenType::Print => "print",
+            TokenType::Return => "return",
+            TokenType::Super => "super",
+            TokenType::This => "this",
+            TokenType::True => "true",
+            TokenType::Var => "var",
+            TokenType::While => "while",
+            TokenType::Eof => "",
+            TokenType::Identifier
+            | TokenType::String
+            | TokenType::Number
+            | TokenType::Error
+            | TokenType::Whitespace
+            | TokenType::Comment => return None,
+        };
+        Some(text)
+    }
+}
+
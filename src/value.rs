@@ -0,0 +1,123 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+use crate::callable::LoxCallable;
+use crate::class::{LoxClass, LoxInstance};
+use crate::symbol::Symbol;
+use crate::token::Literal;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Str(LoxString),
+    Callable(Rc<dyn LoxCallable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+}
+
+impl Value {
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Nil => false,
+            Value::Bool(b) => *b,
+            _ => true,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::Str(l), Value::Str(r)) => l == r,
+            (Value::Callable(l), Value::Callable(r)) => Rc::ptr_eq(l, r),
+            (Value::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
+            (Value::Instance(l), Value::Instance(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl From<&Option<Literal>> for Value {
+    fn from(literal: &Option<Literal>) -> Self {
+        match literal {
+            None => Value::Nil,
+            Some(Literal::True) => Value::Bool(true),
+            Some(Literal::False) => Value::Bool(false),
+            Some(Literal::Number(n)) => Value::Number(*n),
+            Some(Literal::Str(s)) | Some(Literal::Identifier(s)) => Value::Str(LoxString::Interned(*s)),
+        }
+    }
+}
+
+/// The text of a string value. String literals share the text the scanner interned,
+/// and strings built at runtime are reference counted, so copying a value never
+/// copies its text.
+#[derive(Debug, Clone)]
+pub enum LoxString {
+    Interned(Symbol),
+    Owned(Rc<str>),
+}
+
+impl Deref for LoxString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            LoxString::Interned(symbol) => symbol.as_str(),
+            LoxString::Owned(text) => text,
+        }
+    }
+}
+
+impl PartialEq for LoxString {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (LoxString::Interned(l), LoxString::Interned(r)) => l == r,
+            _ => **self == **other,
+        }
+    }
+}
+
+impl From<Symbol> for LoxString {
+    fn from(symbol: Symbol) -> Self {
+        LoxString::Interned(symbol)
+    }
+}
+
+impl From<String> for LoxString {
+    fn from(text: String) -> Self {
+        LoxString::Owned(text.into())
+    }
+}
+
+impl From<&str> for LoxString {
+    fn from(text: &str) -> Self {
+        LoxString::Owned(text.into())
+    }
+}
+
+impl fmt::Display for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Callable(c) => write!(f, "{}", c),
+            Value::Class(c) => write!(f, "{}", c),
+            Value::Instance(i) => write!(f, "{}", i.borrow()),
+        }
+    }
+}
@@ -0,0 +1,635 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
+
+use super::chunk::{Constant, Function, OpCode};
+use super::disassembler::Disassembler;
+use super::errors::LoxError;
+use super::heap::{BoundMethod, Class, Closure, Heap, Instance, Native, Obj, ObjRef, Upvalue, Value};
+use super::symbol::Symbol;
+use super::token::Token;
+
+/// Maximum call depth; deeper recursion is reported as a stack overflow.
+pub const FRAMES_MAX: usize = 1024;
+
+struct CallFrame {
+    closure: ObjRef,
+    function: Rc<Function>,
+    ip: usize,
+    /// Offset of the instruction being executed, for locating runtime errors.
+    start: usize,
+    /// Stack index of slot 0 of this frame.
+    base: usize,
+}
+
+/// Stack-based virtual machine running code from the [`Compiler`](crate::compiler::Compiler).
+/// Globals and heap objects persist between calls to [`Vm::interpret`], as the
+/// tree-walking interpreter's globals do.
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    globals: HashMap<Symbol, Value>,
+    heap: Heap,
+    /// Upvalues still pointing into the stack, sorted by stack slot.
+    open_upvalues: Vec<ObjRef>,
+    output: Box<dyn Write>,
+    /// Where `--log-gc` writes statistics after each collection.
+    gc_log: Option<Box<dyn Write>>,
+    /// Where `--trace-exec` writes the stack and each instruction before it runs.
+    trace: Option<Box<dyn Write>>,
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self::with_output(Box::new(std::io::stdout()))
+    }
+
+    /// Creates a VM whose `print` statements write to `output` instead of stdout.
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        let mut vm = Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals: HashMap::new(),
+            heap: Heap::new(),
+            open_upvalues: Vec::new(),
+            output,
+            gc_log: None,
+            trace: None,
+        };
+        let clock = vm.heap.alloc(Obj::Native(Native {
+            name: "clock",
+            arity: 0,
+            function: |_| {
+                let elapsed = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default();
+                Value::Number(elapsed.as_secs_f64())
+            },
+        }));
+        vm.globals.insert(Symbol::CLOCK, Value::Obj(clock));
+        vm
+    }
+
+    /// Before each instruction, writes the stack and the disassembled instruction to `trace`.
+    pub fn with_trace(mut self, trace: Box<dyn Write>) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Collects garbage before every allocation instead of when the heap outgrows
+    /// its threshold.
+    pub fn with_stress_gc(mut self) -> Self {
+        self.heap = std::mem::take(&mut self.heap).with_stress(true);
+        self
+    }
+
+    /// After each garbage collection, writes its statistics to `log`.
+    pub fn with_gc_log(mut self, log: Box<dyn Write>) -> Self {
+        self.gc_log = Some(log);
+        self
+    }
+
+    /// Runs a compiled script. After a runtime error the stack is cleared, so the VM
+    /// can keep running further scripts.
+    pub fn interpret(&mut self, script: Rc<Function>) -> Result<(), LoxError> {
+        let closure = self.alloc(Obj::Closure(Closure { function: script.clone(), upvalues: Vec::new() }));
+        self.push(Value::Obj(closure));
+        self.frames.push(CallFrame { closure, function: script, ip: 0, start: 0, base: 0 });
+        let result = self.run();
+        if result.is_err() {
+            // Closures that escaped before the error keep the values they captured.
+            self.close_upvalues(0);
+            self.stack.clear();
+            self.frames.clear();
+        }
+        result
+    }
+
+    fn run(&mut self) -> Result<(), LoxError> {
+        loop {
+            if self.trace.is_some() {
+                self.trace_instruction()?;
+            }
+            let frame = self.frame_mut();
+            frame.start = frame.ip;
+            let op = OpCode::from_byte(self.read_byte()).expect("the compiler emits valid opcodes");
+            match op {
+                OpCode::Constant => {
+                    let value = self.read_constant();
+                    self.push(value);
+                },
+                OpCode::Nil => self.push(Value::Nil),
+                OpCode::True => self.push(Value::Bool(true)),
+                OpCode::False => self.push(Value::Bool(false)),
+                OpCode::Pop => {
+                    self.pop();
+                },
+                OpCode::GetLocal => {
+                    let slot = self.frame().base + self.read_byte() as usize;
+                    self.push(self.stack[slot]);
+                },
+                OpCode::SetLocal => {
+                    let slot = self.frame().base + self.read_byte() as usize;
+                    self.stack[slot] = self.peek(0);
+                },
+                OpCode::GetGlobal => {
+                    let name = self.read_name();
+                    match self.globals.get(&name) {
+                        Some(&value) => self.push(value),
+                        None => return Err(LoxError::UndefinedVariable(self.site())),
+                    }
+                },
+                OpCode::DefineGlobal => {
+                    let name = self.read_name();
+                    let value = self.pop();
+                    self.globals.insert(name, value);
+                },
+                OpCode::SetGlobal => {
+                    let name = self.read_name();
+                    let value = self.peek(0);
+                    match self.globals.get_mut(&name) {
+                        Some(global) => *global = value,
+                        None => return Err(LoxError::UndefinedVariable(self.site())),
+                    }
+                },
+                OpCode::GetUpvalue => {
+                    let index = self.read_byte();
+                    let upvalue = self.upvalue(index);
+                    let value = match self.heap.get(upvalue) {
+                        Obj::Upvalue(Upvalue::Open(slot)) => self.stack[*slot],
+                        Obj::Upvalue(Upvalue::Closed(value)) => *value,
+                        other => panic!("expected an upvalue, found {other:?}"),
+                    };
+                    self.push(value);
+                },
+                OpCode::SetUpvalue => {
+                    let index = self.read_byte();
+                    let upvalue = self.upvalue(index);
+                    let value = self.peek(0);
+                    match self.heap.get_mut(upvalue) {
+                        Obj::Upvalue(Upvalue::Open(slot)) => {
+                            let slot = *slot;
+                            self.stack[slot] = value;
+                        },
+                        Obj::Upvalue(closed) => *closed = Upvalue::Closed(value),
+                        other => panic!("expected an upvalue, found {other:?}"),
+                    }
+                },
+                OpCode::GetProperty => {
+                    let name = self.read_name();
+                    let Some(instance) = self.instance(self.peek(0)) else {
+                        return Err(LoxError::OnlyInstancesHaveProperties(self.site()));
+                    };
+                    let Obj::Instance(Instance { class, fields }) = self.heap.get(instance) else { unreachable!() };
+                    if let Some(&value) = fields.get(&name) {
+                        self.pop();
+                        self.push(value);
+                    } else {
+                        let class = *class;
+                        self.bind_method(class, name)?;
+                    }
+                },
+                OpCode::SetProperty => {
+                    let name = self.read_name();
+                    let Some(instance) = self.instance(self.peek(1)) else {
+                        return Err(LoxError::OnlyInstancesHaveFields(self.site()));
+                    };
+                    let value = self.pop();
+                    if let Obj::Instance(instance) = self.heap.get_mut(instance) {
+                        instance.fields.insert(name, value);
+                    }
+                    self.pop();
+                    self.push(value);
+                },
+                OpCode::GetSuper => {
+                    let name = self.read_name();
+                    let Value::Obj(superclass) = self.pop() else { unreachable!("`super` holds a class") };
+                    self.bind_method(superclass, name)?;
+                },
+                OpCode::Equal | OpCode::NotEqual => {
+                    let b = self.pop();
+                    let a = self.pop();
+                    let equal = self.heap.values_equal(a, b);
+                    self.push(Value::Bool(equal == (op == OpCode::Equal)));
+                },
+                OpCode::Greater => self.comparison(|a, b| a > b)?,
+                OpCode::GreaterEqual => self.comparison(|a, b| a >= b)?,
+                OpCode::Less => self.comparison(|a, b| a < b)?,
+                OpCode::LessEqual => self.comparison(|a, b| a <= b)?,
+                OpCode::Add => {
+                    let (a, b) = (self.peek(1), self.peek(0));
+                    let result = match (a, b) {
+                        (Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+                        _ => match (self.heap.str(a), self.heap.str(b)) {
+                            (Some(a), Some(b)) => {
+                                let concatenated = format!("{a}{b}");
+                                Value::Obj(self.alloc(Obj::Str(concatenated.into())))
+                            },
+                            _ => return Err(LoxError::OperandsMustBeNumbersOrStrings(self.site())),
+                        },
+                    };
+                    self.pop();
+                    self.pop();
+                    self.push(result);
+                },
+                OpCode::Subtract => self.arithmetic(|a, b| a - b)?,
+                OpCode::Multiply => self.arithmetic(|a, b| a * b)?,
+                OpCode::Divide => self.arithmetic(|a, b| a / b)?,
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.push(Value::Bool(!value.is_truthy()));
+                },
+                OpCode::Negate => match self.peek(0) {
+                    Value::Number(n) => {
+                        self.pop();
+                        self.push(Value::Number(-n));
+                    },
+                    _ => return Err(LoxError::OperandMustBeNumber(self.site())),
+                },
+                OpCode::Print => {
+                    let value = self.pop();
+                    writeln!(self.output, "{}", self.heap.display(value)).map_err(LoxError::Io)?;
+                },
+                OpCode::Jump => {
+                    let offset = self.read_u16() as usize;
+                    self.frame_mut().ip += offset;
+                },
+                OpCode::JumpIfFalse => {
+                    let offset = self.read_u16() as usize;
+                    if !self.peek(0).is_truthy() {
+                        self.frame_mut().ip += offset;
+                    }
+                },
+                OpCode::Loop => {
+                    let offset = self.read_u16() as usize;
+                    self.frame_mut().ip -= offset;
+                },
+                OpCode::Call => {
+                    let argc = self.read_byte() as usize;
+                    self.call_value(self.peek(argc), argc)?;
+                },
+                OpCode::Closure => {
+                    let Constant::Function(function) = self.read_constant_entry() else {
+                        unreachable!("closures are made from function constants")
+                    };
+                    let base = self.frame().base;
+                    let enclosing = self.frame().closure;
+                    let upvalues = function.upvalues.iter().map(|descriptor| {
+                        if descriptor.is_local {
+                            self.capture_upvalue(base + descriptor.index as usize)
+                        } else {
+                            self.heap.closure(enclosing).upvalues[descriptor.index as usize]
+                        }
+                    }).collect();
+                    let closure = self.alloc(Obj::Closure(Closure { function, upvalues }));
+                    self.push(Value::Obj(closure));
+                },
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                },
+                OpCode::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().expect("returning from a frame");
+                    self.close_upvalues(frame.base);
+                    self.stack.truncate(frame.base);
+                    if self.frames.is_empty() {
+                        return Ok(());
+                    }
+                    self.push(result);
+                },
+                OpCode::Class => {
+                    let name = self.read_name();
+                    let class = self.alloc(Obj::Class(Class { name, methods: HashMap::new() }));
+                    self.push(Value::Obj(class));
+                },
+                OpCode::Inherit => {
+                    let Some(superclass) = self.class(self.peek(1)) else {
+                        return Err(LoxError::SuperclassMustBeClass(self.site()));
+                    };
+                    let methods = self.heap.class(superclass).methods.clone();
+                    let Value::Obj(subclass) = self.pop() else { unreachable!("a class was just created") };
+                    if let Obj::Class(subclass) = self.heap.get_mut(subclass) {
+                        subclass.methods.extend(methods);
+                    }
+                },
+                OpCode::Method => {
+                    let name = self.read_name();
+                    let Value::Obj(method) = self.pop() else { unreachable!("methods are closures") };
+                    let Value::Obj(class) = self.peek(0) else { unreachable!("methods are defined on a class") };
+                    if let Obj::Class(class) = self.heap.get_mut(class) {
+                        class.methods.insert(name, method);
+                    }
+                },
+            }
+        }
+    }
+
+    fn trace_instruction(&mut self) -> Result<(), LoxError> {
+        let mut line = " ".repeat(10);
+        for &value in &self.stack {
+            line.push_str(&format!("[ {} ]", self.heap.display(value)));
+        }
+        let frame = self.frame();
+        let (instruction, _) = Disassembler::new().instruction(&frame.function.chunk, frame.ip);
+        let trace = self.trace.as_mut().expect("tracing is enabled");
+        writeln!(trace, "{line}\n{instruction}").map_err(LoxError::Io)
+    }
+
+    /// Allocates `obj`, collecting garbage first if the heap is due for it. Anything
+    /// the VM still needs must be reachable from the roots before calling this.
+    fn alloc(&mut self, obj: Obj) -> ObjRef {
+        if self.heap.should_collect() {
+            self.collect_garbage();
+        }
+        self.heap.alloc(obj)
+    }
+
+    fn collect_garbage(&mut self) {
+        let mut roots = self.stack.clone();
+        roots.extend(self.frames.iter().map(|frame| Value::Obj(frame.closure)));
+        roots.extend(self.globals.values().copied());
+        roots.extend(self.open_upvalues.iter().map(|&upvalue| Value::Obj(upvalue)));
+        let stats = self.heap.collect(&roots);
+        if let Some(log) = &mut self.gc_log {
+            // Statistics are diagnostics; failing to write them must not fail the program.
+            let _ = writeln!(log, "{stats}");
+        }
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().expect("running inside a frame")
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("running inside a frame")
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frame_mut();
+        let byte = frame.function.chunk.code[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        let frame = self.frame_mut();
+        let value = frame.function.chunk.read_u16(frame.ip);
+        frame.ip += 2;
+        value
+    }
+
+    fn read_constant_entry(&mut self) -> Constant {
+        let index = self.read_u16() as usize;
+        self.frame().function.chunk.constants[index].clone()
+    }
+
+    fn read_constant(&mut self) -> Value {
+        match self.read_constant_entry() {
+            Constant::Number(n) => Value::Number(n),
+            Constant::Str(s) => Value::Obj(self.alloc(Obj::Str(s.into()))),
+            Constant::Function(_) => unreachable!("functions are loaded with OP_CLOSURE"),
+        }
+    }
+
+    fn read_name(&mut self) -> Symbol {
+        match self.read_constant_entry() {
+            Constant::Str(s) => s,
+            other => unreachable!("expected a name constant, found {other:?}"),
+        }
+    }
+
+    /// The token the current instruction reports runtime errors at.
+    fn site(&self) -> Token {
+        let frame = self.frame();
+        frame.function.chunk.site(frame.start).cloned().expect("fallible instructions record their site")
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("the compiler keeps the stack balanced")
+    }
+
+    fn peek(&self, distance: usize) -> Value {
+        self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn instance(&self, value: Value) -> Option<ObjRef> {
+        match value {
+            Value::Obj(obj) if matches!(self.heap.get(obj), Obj::Instance(_)) => Some(obj),
+            _ => None,
+        }
+    }
+
+    fn class(&self, value: Value) -> Option<ObjRef> {
+        match value {
+            Value::Obj(obj) if matches!(self.heap.get(obj), Obj::Class(_)) => Some(obj),
+            _ => None,
+        }
+    }
+
+    fn upvalue(&self, index: u8) -> ObjRef {
+        self.heap.closure(self.frame().closure).upvalues[index as usize]
+    }
+
+    fn number_operands(&self) -> Result<(f64, f64), LoxError> {
+        match (self.peek(1), self.peek(0)) {
+            (Value::Number(a), Value::Number(b)) => Ok((a, b)),
+            _ => Err(LoxError::OperandsMustBeNumbers(self.site())),
+        }
+    }
+
+    fn arithmetic(&mut self, op: fn(f64, f64) -> f64) -> Result<(), LoxError> {
+        let (a, b) = self.number_operands()?;
+        self.pop();
+        self.pop();
+        self.push(Value::Number(op(a, b)));
+        Ok(())
+    }
+
+    fn comparison(&mut self, op: fn(f64, f64) -> bool) -> Result<(), LoxError> {
+        let (a, b) = self.number_operands()?;
+        self.pop();
+        self.pop();
+        self.push(Value::Bool(op(a, b)));
+        Ok(())
+    }
+
+    /// Replaces the receiver on top of the stack with its method `name` bound to it.
+    fn bind_method(&mut self, class: ObjRef, name: Symbol) -> Result<(), LoxError> {
+        let Some(&method) = self.heap.class(class).methods.get(&name) else {
+            return Err(LoxError::UndefinedProperty(self.site()));
+        };
+        // The receiver stays on the stack until the bound method is allocated, so that
+        // a collection cannot free it.
+        let receiver = self.peek(0);
+        let bound = self.alloc(Obj::BoundMethod(BoundMethod { receiver, method }));
+        self.pop();
+        self.push(Value::Obj(bound));
+        Ok(())
+    }
+
+    /// Calls `callee`, which sits on the stack below its `argc` arguments.
+    fn call_value(&mut self, callee: Value, argc: usize) -> Result<(), LoxError> {
+        let Value::Obj(obj) = callee else {
+            return Err(LoxError::NotCallable(self.site()));
+        };
+        let base = self.stack.len() - argc - 1;
+        match self.heap.get(obj) {
+            Obj::Closure(_) => self.call(obj, argc),
+            Obj::BoundMethod(bound) => {
+                let method = bound.method;
+                self.stack[base] = bound.receiver;
+                self.call(method, argc)
+            },
+            Obj::Class(class) => {
+                let initializer = class.methods.get(&Symbol::INIT).copied();
+                let instance = self.alloc(Obj::Instance(Instance { class: obj, fields: HashMap::new() }));
+                self.stack[base] = Value::Obj(instance);
+                match initializer {
+                    Some(initializer) => self.call(initializer, argc),
+                    None if argc != 0 => Err(LoxError::ArityMismatch(self.site(), 0, argc)),
+                    None => Ok(()),
+                }
+            },
+            Obj::Native(native) => {
+                if argc != native.arity {
+                    return Err(LoxError::ArityMismatch(self.site(), native.arity, argc));
+                }
+                let result = (native.function)(&self.stack[base + 1..]);
+                self.stack.truncate(base);
+                self.push(result);
+                Ok(())
+            },
+            _ => Err(LoxError::NotCallable(self.site())),
+        }
+    }
+
+    fn call(&mut self, closure: ObjRef, argc: usize) -> Result<(), LoxError> {
+        let function = self.heap.closure(closure).function.clone();
+        if argc != function.arity {
+            return Err(LoxError::ArityMismatch(self.site(), function.arity, argc));
+        }
+        if self.frames.len() == FRAMES_MAX {
+            return Err(LoxError::StackOverflow(self.site()));
+        }
+        let base = self.stack.len() - argc - 1;
+        self.frames.push(CallFrame { closure, function, ip: 0, start: 0, base });
+        Ok(())
+    }
+
+    /// The open upvalue for stack `slot`, created unless a closure already captured it.
+    fn capture_upvalue(&mut self, slot: usize) -> ObjRef {
+        let position = self.open_upvalues.partition_point(|&upvalue| self.open_slot(upvalue) < slot);
+        if let Some(&upvalue) = self.open_upvalues.get(position) {
+            if self.open_slot(upvalue) == slot {
+                return upvalue;
+            }
+        }
+        let upvalue = self.alloc(Obj::Upvalue(Upvalue::Open(slot)));
+        self.open_upvalues.insert(position, upvalue);
+        upvalue
+    }
+
+    /// Closes every open upvalue pointing at stack slot `first` or above.
+    fn close_upvalues(&mut self, first: usize) {
+        let position = self.open_upvalues.partition_point(|&upvalue| self.open_slot(upvalue) < first);
+        for upvalue in self.open_upvalues.split_off(position) {
+            let value = self.stack[self.open_slot(upvalue)];
+            *self.heap.get_mut(upvalue) = Obj::Upvalue(Upvalue::Closed(value));
+        }
+    }
+
+    fn open_slot(&self, upvalue: ObjRef) -> usize {
+        match self.heap.get(upvalue) {
+            Obj::Upvalue(Upvalue::Open(slot)) => *slot,
+            other => panic!("expected an open upvalue, found {other:?}"),
+        }
+    }
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Compiler;
+    use crate::parse;
+    use crate::span::SourceMap;
+    use crate::tests::Output;
+
+    fn run(source: &str) -> (String, Result<(), LoxError>) {
+        let output = Output::default();
+        let script = Compiler::new(&SourceMap::new(source.as_bytes())).compile(&parse(source.as_bytes()).unwrap()).unwrap();
+        let result = Vm::with_output(Box::new(output.clone())).interpret(script);
+        let printed = output.take();
+        (printed, result)
+    }
+
+    #[test]
+    fn test_closures_and_classes() {
+        let source = "\
+fun counter() { var n = 0; fun inc() { n = n + 1; return n; } return inc; }
+var c = counter(); c(); print c();
+class A { init(x) { this.x = x; } get() { return this.x; } }
+class B < A { get() { return \"B\" + super.get(); } }
+print B(\"x\").get();
+print B;";
+        let (printed, result) = run(source);
+        assert!(result.is_ok());
+        assert_eq!(printed, "2\nBx\nB\n");
+    }
+
+    #[test]
+    fn test_trace_shows_stack_before_each_instruction() {
+        let trace = Output::default();
+        let source = "print -1;";
+        let script = Compiler::new(&SourceMap::new(source.as_bytes())).compile(&parse(source.as_bytes()).unwrap()).unwrap();
+        let mut vm = Vm::with_output(Box::new(Output::default())).with_trace(Box::new(trace.clone()));
+        vm.interpret(script).unwrap();
+        let trace = trace.take();
+        assert!(trace.starts_with("          [ <script> ]\n0000    1 OP_CONSTANT         0 '1'\n"));
+        assert!(trace.contains("          [ <script> ][ -1 ]\n0004    | OP_PRINT\n"));
+    }
+
+    #[test]
+    fn test_garbage_is_collected() {
+        let source = "class A { init() { this.me = this; this.m = this.f; } f() {} }\nfor (var i = 0; i < 1000; i = i + 1) A();";
+        let script = Compiler::new(&SourceMap::new(source.as_bytes())).compile(&parse(source.as_bytes()).unwrap()).unwrap();
+        let log = Output::default();
+        let mut vm = Vm::with_output(Box::new(Output::default())).with_stress_gc().with_gc_log(Box::new(log.clone()));
+        vm.interpret(script).unwrap();
+        assert!(vm.heap.len() < 10, "{} objects survived", vm.heap.len());
+        let log = log.take();
+        assert!(log.lines().count() > 1000);
+        assert!(log.starts_with("[gc 1] freed 0 objects (0 bytes), 1 live ("));
+    }
+
+    #[test]
+    fn test_runtime_errors_report_their_site() {
+        let (printed, result) = run("print 1;\nprint -\"a\";");
+        assert_eq!(printed, "1\n");
+        assert_eq!(result.unwrap_err().to_string(), "[line 2] Error at '-': Operand must be a number.");
+
+        let (_, result) = run("fun f() { f(); }\nf();");
+        assert!(matches!(result, Err(LoxError::StackOverflow(token)) if token.line == 1));
+    }
+
+    #[test]
+    fn test_escaped_closures_survive_runtime_errors() {
+        let output = Output::default();
+        let mut vm = Vm::with_output(Box::new(output.clone()));
+        let compile = |source: &str| Compiler::new(&SourceMap::new(source.as_bytes())).compile(&parse(source.as_bytes()).unwrap()).unwrap();
+        let source = "var f;\nfun g() { var x = 1; fun h() { return x; } f = h; nil(); }\ng();";
+        assert!(matches!(vm.interpret(compile(source)), Err(LoxError::NotCallable(_))));
+        vm.interpret(compile("print f();")).unwrap();
+        assert_eq!(output.take(), "1\n");
+    }
+}
@@ -0,0 +1,76 @@
+//! Runs every program in `tests/conformance` on both backends and checks that they
+//! print the same output and fail with the same errors.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+
+use rinterpreter::{Backend, Lox, RunResult};
+
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Printed output, error messages and exit code of running `source` on `backend`.
+fn run(backend: Backend, source: &[u8]) -> (String, Vec<String>, i32) {
+    run_with(backend, source, |lox| lox)
+}
+
+fn run_with(backend: Backend, source: &[u8], configure: impl FnOnce(Lox) -> Lox) -> (String, Vec<String>, i32) {
+    let output = Output::default();
+    let result = configure(Lox::with_output(backend, Box::new(output.clone()))).run(source);
+    let errors = match &result {
+        RunResult::Success(_) => Vec::new(),
+        RunResult::StaticErrors(errors) => errors.iter().map(ToString::to_string).collect(),
+        RunResult::RuntimeError(error) => vec![error.to_string()],
+    };
+    let printed = String::from_utf8(output.0.take()).expect("Lox prints UTF-8");
+    (printed, errors, result.exit_code())
+}
+
+#[test]
+fn test_backends_agree() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+    let mut programs: Vec<_> = std::fs::read_dir(&directory)
+        .expect("the conformance directory exists")
+        .map(|entry| entry.expect("the directory is readable").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "lox"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty());
+
+    for program in programs {
+        let source = std::fs::read(&program).expect("the program is readable");
+        let tree = run(Backend::TreeWalk, &source);
+        let vm = run(Backend::Vm, &source);
+        assert_eq!(tree, vm, "backends disagree on {}", program.display());
+        // Collecting on every allocation frees anything the VM failed to keep reachable.
+        let stress_gc = run_with(Backend::Vm, &source, Lox::with_stress_gc);
+        assert_eq!(tree, stress_gc, "the VM under --stress-gc disagrees on {}", program.display());
+    }
+}
+
+#[test]
+fn test_expected_failures() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+    let run_vm = |name: &str| run(Backend::Vm, &std::fs::read(directory.join(name)).unwrap());
+
+    assert_eq!(run_vm("runtime_error_call.lox"), (
+        "3\n".to_string(),
+        vec!["[line 3] Error at ')': Expected 2 arguments but got 1.".to_string()],
+        70,
+    ));
+    assert_eq!(run_vm("static_error.lox").2, 65);
+    let (printed, errors, _) = run_vm("runtime_error_stack_overflow.lox");
+    assert_eq!((printed.as_str(), errors[0].as_str()), ("deep\n", "[line 3] Error at ')': Stack overflow."));
+}